@@ -0,0 +1,40 @@
+extern crate advent_of_code_2021;
+
+use std::env;
+use std::fs;
+use std::process;
+
+use advent_of_code_2021::explore;
+
+// cargo run --bin explore -- <day> [input file]
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let day = match args
+        .get(1)
+        .and_then(|d| d.trim_start_matches("day").parse::<u32>().ok())
+    {
+        Some(day) => day,
+        None => {
+            eprintln!("usage: {} <day> [input file]", args[0]);
+            process::exit(2);
+        }
+    };
+    let path = args
+        .get(2)
+        .cloned()
+        .unwrap_or_else(|| format!("input/2021/day{}.txt", day));
+
+    let input = fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("couldn't read {}: {}", path, e);
+        process::exit(1);
+    });
+    let sim = explore::simulation(day, &input).unwrap_or_else(|| {
+        eprintln!("day {} has nothing to explore", day);
+        process::exit(1);
+    });
+
+    if let Err(e) = explore::run(sim) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
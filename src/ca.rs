@@ -0,0 +1,561 @@
+use fxhash::{FxHashMap, FxHashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+pub type Point = (i32, i32);
+
+// Inclusive rectangle of cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn new(cols: usize, rows: usize) -> Bounds {
+        Bounds {
+            min: (0, 0),
+            max: (cols as i32 - 1, rows as i32 - 1),
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        (self.max.0 - self.min.0 + 1).max(0) as usize
+    }
+
+    pub fn rows(&self) -> usize {
+        (self.max.1 - self.min.1 + 1).max(0) as usize
+    }
+
+    pub fn area(&self) -> usize {
+        self.cols() * self.rows()
+    }
+
+    pub fn contains(&self, (x, y): Point) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
+
+    pub fn grow(&self, by: i32) -> Bounds {
+        Bounds {
+            min: (self.min.0 - by, self.min.1 - by),
+            max: (self.max.0 + by, self.max.1 + by),
+        }
+    }
+
+    // Wrap a point around the edges, as if the grid were a torus
+    pub fn wrap(&self, (x, y): Point) -> Point {
+        (
+            self.min.0 + (x - self.min.0).rem_euclid(self.cols() as i32),
+            self.min.1 + (y - self.min.1).rem_euclid(self.rows() as i32),
+        )
+    }
+
+    // Row-major, like reading the puzzle input
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let (min, max) = (self.min, self.max);
+        (min.1..=max.1).flat_map(move |y| (min.0..=max.0).map(move |x| (x, y)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighborhood {
+    offsets: Vec<Point>,
+}
+
+impl Neighborhood {
+    // N, E, S, W
+    pub fn von_neumann() -> Neighborhood {
+        Neighborhood::custom(vec![(0, -1), (1, 0), (0, 1), (-1, 0)])
+    }
+
+    // The 8 surrounding cells, in reading order
+    pub fn moore() -> Neighborhood {
+        let mut offsets = Neighborhood::block().offsets;
+        offsets.remove(4);
+        Neighborhood::custom(offsets)
+    }
+
+    // The 3x3 square including the cell itself, in reading order
+    pub fn block() -> Neighborhood {
+        Neighborhood::custom(
+            (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                .collect(),
+        )
+    }
+
+    pub fn custom(offsets: Vec<Point>) -> Neighborhood {
+        Neighborhood { offsets }
+    }
+
+    pub fn offsets(&self) -> &[Point] {
+        &self.offsets
+    }
+
+    // How far the neighbourhood reaches in any direction
+    pub fn radius(&self) -> i32 {
+        self.offsets
+            .iter()
+            .map(|&(dx, dy)| dx.abs().max(dy.abs()))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+// What lies beyond the edge of the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    // Constant background cells
+    Fixed,
+    // Edges wrap around
+    Toroidal,
+    // The grid grows every step, and the background evolves by the rule too
+    Infinite,
+}
+
+pub trait Rule {
+    type Cell: Copy + Eq + Hash;
+
+    // The new state of a cell, given its neighbours in neighbourhood order
+    fn next(&self, phase: usize, cell: Self::Cell, neighbors: &[Self::Cell]) -> Self::Cell;
+
+    // Number of sub-steps making up one generation
+    fn phases(&self) -> usize {
+        1
+    }
+
+    // Whether a phase is repeated until no cell changes
+    fn settles(&self, _phase: usize) -> bool {
+        false
+    }
+}
+
+pub trait Storage<C: Copy + Eq>: Clone {
+    fn blank(bounds: Bounds, background: C) -> Self;
+    fn reset(&mut self, bounds: Bounds, background: C);
+    fn bounds(&self) -> Bounds;
+    // Only valid for points within bounds
+    fn get(&self, p: Point) -> C;
+    fn set(&mut self, p: Point, c: C);
+    // Every cell that isn't background
+    fn cells(&self) -> Vec<(Point, C)>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dense<C> {
+    bounds: Bounds,
+    background: C,
+    cells: Vec<C>,
+}
+
+impl<C> Dense<C> {
+    fn index(&self, (x, y): Point) -> usize {
+        (y - self.bounds.min.1) as usize * self.bounds.cols() + (x - self.bounds.min.0) as usize
+    }
+}
+
+impl<C: Copy + Eq> Storage<C> for Dense<C> {
+    fn blank(bounds: Bounds, background: C) -> Self {
+        Dense {
+            bounds,
+            background,
+            cells: vec![background; bounds.area()],
+        }
+    }
+
+    fn reset(&mut self, bounds: Bounds, background: C) {
+        self.bounds = bounds;
+        self.background = background;
+        self.cells.clear();
+        self.cells.resize(bounds.area(), background);
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    fn get(&self, p: Point) -> C {
+        self.cells[self.index(p)]
+    }
+
+    fn set(&mut self, p: Point, c: C) {
+        let i = self.index(p);
+        self.cells[i] = c;
+    }
+
+    fn cells(&self) -> Vec<(Point, C)> {
+        self.bounds
+            .points()
+            .zip(self.cells.iter().copied())
+            .filter(|&(_, c)| c != self.background)
+            .collect()
+    }
+}
+
+// Only cells that differ from the background are stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sparse<C> {
+    bounds: Bounds,
+    background: C,
+    cells: FxHashMap<Point, C>,
+}
+
+impl<C: Copy + Eq> Storage<C> for Sparse<C> {
+    fn blank(bounds: Bounds, background: C) -> Self {
+        Sparse {
+            bounds,
+            background,
+            cells: FxHashMap::default(),
+        }
+    }
+
+    fn reset(&mut self, bounds: Bounds, background: C) {
+        self.bounds = bounds;
+        self.background = background;
+        self.cells.clear();
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    fn get(&self, p: Point) -> C {
+        *self.cells.get(&p).unwrap_or(&self.background)
+    }
+
+    fn set(&mut self, p: Point, c: C) {
+        if c == self.background {
+            self.cells.remove(&p);
+        } else {
+            self.cells.insert(p, c);
+        }
+    }
+
+    fn cells(&self) -> Vec<(Point, C)> {
+        self.cells.iter().map(|(&p, &c)| (p, c)).collect()
+    }
+}
+
+// Read a character grid into storage, with (0, 0) at the top left
+pub fn from_grid<C, S>(input: &str, background: C, parse: impl Fn(u8) -> C) -> S
+where
+    C: Copy + Eq,
+    S: Storage<C>,
+{
+    let rows: Vec<&str> = input.split_whitespace().collect();
+    let cols = rows.first().map_or(0, |r| r.len());
+    let mut storage = S::blank(Bounds::new(cols, rows.len()), background);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, ch) in row.bytes().enumerate() {
+            storage.set((x as i32, y as i32), parse(ch));
+        }
+    }
+    storage
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    // This generation was identical to the one before it
+    FixedPoint { generation: usize },
+    // This generation repeats an earlier one
+    Cycle { start: usize, period: usize },
+    // Gave up before either happened
+    Unsettled,
+}
+
+#[derive(Clone)]
+pub struct Automaton<R: Rule, S> {
+    rule: R,
+    neighborhood: Neighborhood,
+    boundary: Boundary,
+    background: R::Cell,
+    cur: S,
+    next: S,
+    generation: usize,
+}
+
+impl<R, S> Automaton<R, S>
+where
+    R: Rule,
+    S: Storage<R::Cell>,
+{
+    pub fn new(
+        rule: R,
+        neighborhood: Neighborhood,
+        boundary: Boundary,
+        background: R::Cell,
+        cells: S,
+    ) -> Self {
+        Automaton {
+            rule,
+            neighborhood,
+            boundary,
+            background,
+            next: cells.clone(),
+            cur: cells,
+            generation: 0,
+        }
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn background(&self) -> R::Cell {
+        self.background
+    }
+
+    pub fn bounds(&self) -> Bounds {
+        self.cur.bounds()
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.cur
+    }
+
+    // Any cell, including those beyond the edge of the grid
+    pub fn get(&self, p: Point) -> R::Cell {
+        let bounds = self.cur.bounds();
+        if bounds.contains(p) {
+            self.cur.get(p)
+        } else if self.boundary == Boundary::Toroidal {
+            self.cur.get(bounds.wrap(p))
+        } else {
+            self.background
+        }
+    }
+
+    // One character per cell, one line per row
+    pub fn render(&self, ch: impl Fn(R::Cell) -> char) -> String {
+        let bounds = self.cur.bounds();
+        let mut out = String::with_capacity(bounds.area() + bounds.rows());
+        for y in bounds.min.1..=bounds.max.1 {
+            for x in bounds.min.0..=bounds.max.0 {
+                out.push(ch(self.cur.get((x, y))));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // Cells within bounds matching the predicate
+    pub fn count(&self, pred: impl Fn(R::Cell) -> bool) -> usize {
+        if pred(self.background) {
+            self.cur
+                .bounds()
+                .points()
+                .filter(|&p| pred(self.cur.get(p)))
+                .count()
+        } else {
+            self.cur.cells().iter().filter(|&&(_, c)| pred(c)).count()
+        }
+    }
+
+    // Advance one generation, returning how many cell updates changed something
+    pub fn step(&mut self) -> usize {
+        let mut changed = 0;
+        for phase in 0..self.rule.phases() {
+            if self.rule.settles(phase) {
+                loop {
+                    let c = self.apply(phase);
+                    changed += c;
+                    if c == 0 {
+                        break;
+                    }
+                }
+            } else {
+                changed += self.apply(phase);
+            }
+        }
+        self.generation += 1;
+        changed
+    }
+
+    pub fn run(&mut self, generations: usize) -> usize {
+        (0..generations).map(|_| self.step()).sum()
+    }
+
+    // Step until the grid stops changing or repeats itself. States are compared
+    // by fingerprint, so a hash collision could report a false cycle.
+    pub fn run_until_stable(&mut self, limit: usize) -> Outcome {
+        let mut seen: HashMap<u64, usize> = HashMap::new();
+        let mut prev = self.fingerprint();
+        seen.insert(prev, self.generation);
+
+        for _ in 0..limit {
+            self.step();
+            let fp = self.fingerprint();
+            if fp == prev {
+                return Outcome::FixedPoint {
+                    generation: self.generation,
+                };
+            }
+            if let Some(&start) = seen.get(&fp) {
+                return Outcome::Cycle {
+                    start,
+                    period: self.generation - start,
+                };
+            }
+            seen.insert(fp, self.generation);
+            prev = fp;
+        }
+
+        Outcome::Unsettled
+    }
+
+    // Order-independent hash of the background and every non-background cell.
+    // Bounds are left out so growing infinite grids can still settle.
+    pub fn fingerprint(&self) -> u64 {
+        let hash = |v: &dyn Fn(&mut DefaultHasher)| {
+            let mut h = DefaultHasher::new();
+            v(&mut h);
+            h.finish()
+        };
+        self.cur
+            .cells()
+            .iter()
+            .map(|cell| hash(&|h| cell.hash(h)))
+            .fold(hash(&|h| self.background.hash(h)), u64::wrapping_add)
+    }
+
+    fn apply(&mut self, phase: usize) -> usize {
+        let bg = self.background;
+        let all_bg = vec![bg; self.neighborhood.offsets().len()];
+        let bg_next = self.rule.next(phase, bg, &all_bg);
+
+        let bounds = match self.boundary {
+            Boundary::Infinite => self.cur.bounds().grow(self.neighborhood.radius()),
+            _ => self.cur.bounds(),
+        };
+        let background = match self.boundary {
+            Boundary::Infinite => bg_next,
+            _ => bg,
+        };
+
+        // If background cells stay background, only cells near something else
+        // can change; otherwise everything has to be looked at.
+        let candidates: Vec<Point> = if bg_next == bg {
+            let mut near: FxHashSet<Point> = FxHashSet::default();
+            for ((x, y), _) in self.cur.cells() {
+                near.insert((x, y));
+                for &(dx, dy) in self.neighborhood.offsets() {
+                    let p = (x - dx, y - dy);
+                    match self.boundary {
+                        Boundary::Toroidal => {
+                            near.insert(bounds.wrap(p));
+                        }
+                        _ if bounds.contains(p) => {
+                            near.insert(p);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            near.into_iter().collect()
+        } else {
+            bounds.points().collect()
+        };
+
+        self.next.reset(bounds, background);
+        let mut neighbors: Vec<R::Cell> = Vec::with_capacity(all_bg.len());
+        let mut changed = 0;
+
+        for (x, y) in candidates {
+            neighbors.clear();
+            for &(dx, dy) in self.neighborhood.offsets() {
+                neighbors.push(self.get((x + dx, y + dy)));
+            }
+            let old = self.get((x, y));
+            let new = self.rule.next(phase, old, &neighbors);
+            if new != old {
+                changed += 1;
+            }
+            self.next.set((x, y), new);
+        }
+
+        std::mem::swap(&mut self.cur, &mut self.next);
+        self.background = background;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Life;
+
+    impl Rule for Life {
+        type Cell = bool;
+
+        fn next(&self, _phase: usize, alive: bool, neighbors: &[bool]) -> bool {
+            let n = neighbors.iter().filter(|&&n| n).count();
+            n == 3 || (alive && n == 2)
+        }
+    }
+
+    fn life<S: Storage<bool>>(input: &str, boundary: Boundary) -> Automaton<Life, S> {
+        let cells = from_grid(input, false, |ch| ch == b'#');
+        Automaton::new(Life, Neighborhood::moore(), boundary, false, cells)
+    }
+
+    const BLINKER: &str = r#".....
+..#..
+..#..
+..#..
+.....
+"#;
+
+    const BLOCK: &str = r#"....
+.##.
+.##.
+....
+"#;
+
+    #[test]
+    fn blinker_cycles() {
+        let mut ca = life::<Dense<bool>>(BLINKER, Boundary::Fixed);
+        assert_eq!(
+            ca.run_until_stable(10),
+            Outcome::Cycle {
+                start: 0,
+                period: 2
+            }
+        );
+        assert_eq!(ca.count(|c| c), 3);
+    }
+
+    #[test]
+    fn block_is_fixed() {
+        let mut ca = life::<Sparse<bool>>(BLOCK, Boundary::Infinite);
+        assert_eq!(
+            ca.run_until_stable(10),
+            Outcome::FixedPoint { generation: 1 }
+        );
+        assert_eq!(ca.count(|c| c), 4);
+    }
+
+    #[test]
+    fn glider_wraps() {
+        let glider = "#..\n.##\n##.\n";
+        let torus = ".".repeat(6) + "\n";
+        let input = glider
+            .lines()
+            .map(|l| format!("{}...\n", l))
+            .collect::<String>()
+            + &torus.repeat(3);
+        let mut dense = life::<Dense<bool>>(&input, Boundary::Toroidal);
+        let mut sparse = life::<Sparse<bool>>(&input, Boundary::Toroidal);
+        // A glider moves one cell diagonally every 4 generations
+        assert_eq!(
+            dense.run_until_stable(100),
+            Outcome::Cycle {
+                start: 0,
+                period: 24
+            }
+        );
+        sparse.run(24);
+        assert_eq!(sparse.fingerprint(), dense.fingerprint());
+    }
+}
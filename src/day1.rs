@@ -1,3 +1,9 @@
+use std::cmp::{max, Ordering};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::ops::Range;
+
 type Depth = usize;
 
 #[aoc_generator(day1)]
@@ -8,23 +14,178 @@ pub fn input_generator(input: &str) -> Vec<Depth> {
         .collect()
 }
 
+// How a series of sliding-window sums moves from one window to the next.
+// Runs count consecutive comparisons going the same way.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SonarStats {
+    pub increases: usize,
+    pub decreases: usize,
+    pub plateaus: usize,
+    pub longest_increasing: usize,
+    pub longest_decreasing: usize,
+}
+
+// Compare every window of k depths with the one after it, in O(k) memory.
+// Neighbouring windows share all but one depth, so comparing their sums is
+// the same as comparing a[i+k] with a[i].
+pub fn analyze(depths: impl IntoIterator<Item = Depth>, k: usize) -> SonarStats {
+    assert!(k > 0, "window size must be at least 1");
+
+    let mut stats = SonarStats::default();
+    let mut window: VecDeque<Depth> = VecDeque::with_capacity(k);
+    let (mut rising, mut falling) = (0, 0);
+
+    for depth in depths {
+        if window.len() == k {
+            let dropped = window.pop_front().unwrap();
+            match depth.cmp(&dropped) {
+                Ordering::Greater => {
+                    stats.increases += 1;
+                    rising += 1;
+                    falling = 0;
+                }
+                Ordering::Less => {
+                    stats.decreases += 1;
+                    falling += 1;
+                    rising = 0;
+                }
+                Ordering::Equal => {
+                    stats.plateaus += 1;
+                    rising = 0;
+                    falling = 0;
+                }
+            }
+            stats.longest_increasing = max(stats.longest_increasing, rising);
+            stats.longest_decreasing = max(stats.longest_decreasing, falling);
+        }
+        window.push_back(depth);
+    }
+
+    stats
+}
+
+// As analyze, reading one depth per line without holding the whole log
+pub fn analyze_reader(reader: impl BufRead, k: usize) -> io::Result<SonarStats> {
+    let mut err: Option<io::Error> = None;
+    let depths = reader.lines().map_while(|l| {
+        let parsed = l.and_then(|l| {
+            l.trim()
+                .parse::<Depth>()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        });
+        parsed.map_err(|e| err = Some(e)).ok()
+    });
+    let stats = analyze(depths, k);
+    match err {
+        Some(e) => Err(e),
+        None => Ok(stats),
+    }
+}
+
+// Thresholds for what counts as a suspicious reading
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnomalyConfig {
+    // How many preceding readings a spike is judged against; 0 disables spikes
+    pub window: usize,
+    // How many standard deviations from the window mean makes a spike
+    pub sigmas: f64,
+    // Changing by more than this from one reading to the next, in either
+    // direction, is a sudden jump
+    pub jump: Depth,
+    // This many identical readings in a row looks like a stuck sensor
+    pub flat_run: usize,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        AnomalyConfig {
+            window: 10,
+            sigmas: 3.0,
+            jump: 100,
+            flat_run: 5,
+        }
+    }
+}
+
+// Indices of every reading that looks wrong
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AnomalyReport {
+    pub spikes: Vec<usize>,
+    pub jumps: Vec<usize>,
+    pub flat: Vec<Range<usize>>,
+}
+
+impl AnomalyReport {
+    pub fn is_clean(&self) -> bool {
+        self.spikes.is_empty() && self.jumps.is_empty() && self.flat.is_empty()
+    }
+}
+
+impl fmt::Display for AnomalyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "spikes: {:?}", self.spikes)?;
+        writeln!(f, "jumps: {:?}", self.jumps)?;
+        writeln!(f, "flat: {:?}", self.flat)
+    }
+}
+
+pub fn anomalies(depths: impl IntoIterator<Item = Depth>, config: &AnomalyConfig) -> AnomalyReport {
+    let mut report = AnomalyReport::default();
+    let mut window: VecDeque<f64> = VecDeque::with_capacity(config.window);
+    let mut prev: Option<Depth> = None;
+    let mut flat_start = 0;
+
+    let end_flat = |report: &mut AnomalyReport, start: usize, end: usize| {
+        if end - start >= config.flat_run {
+            report.flat.push(start..end);
+        }
+    };
+
+    let mut len = 0;
+    for (i, depth) in depths.into_iter().enumerate() {
+        len = i + 1;
+
+        // Spikes need a full window to know what normal looks like. A perfectly
+        // steady window has no spread to judge against, so skip it.
+        if window.len() == config.window && config.window >= 2 {
+            let readings = window.make_contiguous();
+            let mean = statistical::mean(readings);
+            let sd = statistical::standard_deviation(readings, Some(mean));
+            if sd > 0.0 && (depth as f64 - mean).abs() > config.sigmas * sd {
+                report.spikes.push(i);
+            }
+        }
+        if config.window > 0 {
+            if window.len() == config.window {
+                window.pop_front();
+            }
+            window.push_back(depth as f64);
+        }
+
+        if let Some(p) = prev {
+            if depth.abs_diff(p) > config.jump {
+                report.jumps.push(i);
+            }
+            if depth != p {
+                end_flat(&mut report, flat_start, i);
+                flat_start = i;
+            }
+        }
+        prev = Some(depth);
+    }
+    end_flat(&mut report, flat_start, len);
+
+    report
+}
+
 #[aoc(day1, part1)]
 pub fn solve_part1(input: &[Depth]) -> usize {
-    input
-        .windows(2)
-        .map(|ds| -> usize { (ds[1] > ds[0]).into() }) // 1 if increasing, else 0
-        .sum()
+    analyze(input.iter().copied(), 1).increases
 }
 
 #[aoc(day1, part2)]
 pub fn solve_part2(input: &[Depth]) -> usize {
-    input
-        .windows(3)
-        .map(|ds| ds[0] + ds[1] + ds[2]) // Sum 3d window
-        .collect::<Vec<Depth>>()
-        .windows(2)
-        .map(|ds| -> usize { (ds[1] > ds[0]).into() }) // 1 if increasing, else 0
-        .sum()
+    analyze(input.iter().copied(), 3).increases
 }
 
 #[cfg(test)]
@@ -52,4 +213,51 @@ mod tests {
     fn example2() {
         assert_eq!(solve_part2(&input_generator(EXAMPLE_INPUT)), 5)
     }
+
+    #[test]
+    fn streaming() {
+        let depths = input_generator(EXAMPLE_INPUT);
+        let stats = analyze_reader(EXAMPLE_INPUT.as_bytes(), 1).unwrap();
+        assert_eq!(
+            stats,
+            SonarStats {
+                increases: 7,
+                decreases: 2,
+                plateaus: 0,
+                longest_increasing: 3,
+                longest_decreasing: 1,
+            }
+        );
+        // 607 618 618 617 647 716 769 792
+        assert_eq!(analyze(depths, 3).plateaus, 1);
+        assert!(analyze_reader("1\n2\nthree\n".as_bytes(), 1).is_err());
+    }
+
+    #[test]
+    fn anomaly_report() {
+        let depths = input_generator(EXAMPLE_INPUT);
+        assert!(anomalies(depths.iter().copied(), &AnomalyConfig::default()).is_clean());
+
+        let noisy = [
+            100, 102, 101, 103, 102, 101, 103, 102, 400, 103, 103, 103, 103, 103, 103, 104, 300,
+        ];
+        let config = AnomalyConfig {
+            window: 5,
+            ..Default::default()
+        };
+        let report = anomalies(noisy, &config);
+        assert_eq!(report.spikes, vec![8, 16]);
+        assert_eq!(report.jumps, vec![8, 9, 16]);
+        assert_eq!(report.flat, vec![9..15]);
+
+        let report = anomalies(
+            noisy,
+            &AnomalyConfig {
+                window: 0,
+                ..Default::default()
+            },
+        );
+        assert!(report.spikes.is_empty());
+        assert_eq!(report.jumps, vec![8, 9, 16]);
+    }
 }
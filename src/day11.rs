@@ -2,6 +2,9 @@ use std::cmp::{max, min};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::ops::RangeInclusive;
 
+use crate::ca;
+use crate::explore::Simulation;
+
 const ROWS: usize = 10;
 const COLS: usize = 10;
 
@@ -11,7 +14,7 @@ type GridMap = HashMap<Point, u8>;
 
 fn parse_input(input: &str) -> Grid {
     let mut grid = [[0_u8; ROWS]; COLS];
-    for (r, row) in input.trim().split_whitespace().enumerate() {
+    for (r, row) in input.split_whitespace().enumerate() {
         // println!("{}, {:?}", r, row);
         for (c, ch) in row.trim().bytes().enumerate() {
             // println!("{}, {} = {}", r, c, ch);
@@ -23,7 +26,7 @@ fn parse_input(input: &str) -> Grid {
 
 fn parse_input_map(input: &str) -> GridMap {
     let mut grid: GridMap = HashMap::new();
-    for (r, row) in input.trim().split_whitespace().enumerate() {
+    for (r, row) in input.split_whitespace().enumerate() {
         // println!("{}, {:?}", r, row);
         for (c, ch) in row.trim().bytes().enumerate() {
             // println!("{}, {} = {}", r, c, ch);
@@ -195,6 +198,92 @@ fn step_map(grid: &mut GridMap) -> usize {
     flashed.len()
 }
 
+// Energy level, and whether it flashed this step
+type Octopus = (u8, bool);
+
+#[derive(Clone)]
+struct Flashes;
+
+impl ca::Rule for Flashes {
+    type Cell = Octopus;
+
+    fn next(&self, phase: usize, (e, flashed): Octopus, neighbors: &[Octopus]) -> Octopus {
+        match phase {
+            // energy++
+            0 => (e + 1, flashed),
+            // FLASH, until nothing has enough energy left
+            1 if flashed => (e, flashed),
+            1 if e > 9 => (e, true),
+            1 => {
+                let flashing = neighbors.iter().filter(|&&(n, f)| n > 9 && !f).count();
+                (e + flashing as u8, false)
+            }
+            // Everything that flashed goes back to 0
+            _ if flashed => (0, false),
+            _ => (e, flashed),
+        }
+    }
+
+    fn phases(&self) -> usize {
+        3
+    }
+
+    fn settles(&self, phase: usize) -> bool {
+        phase == 1
+    }
+}
+
+fn cavern(input: &str) -> ca::Automaton<Flashes, ca::Dense<Octopus>> {
+    // Outside the cavern counts as already flashed, so it never does
+    let outside = (0, true);
+    let cells = ca::from_grid(input, outside, |ch| (ch - b'0', false));
+    ca::Automaton::new(
+        Flashes,
+        ca::Neighborhood::moore(),
+        ca::Boundary::Fixed,
+        outside,
+        cells,
+    )
+}
+
+// Steppable view of the cavern, for the explorer
+#[derive(Clone)]
+pub struct Octopuses {
+    cavern: ca::Automaton<Flashes, ca::Dense<Octopus>>,
+    flashes: usize,
+    last: usize,
+}
+
+impl Octopuses {
+    pub fn new(input: &str) -> Octopuses {
+        Octopuses {
+            cavern: cavern(input),
+            flashes: 0,
+            last: 0,
+        }
+    }
+}
+
+impl Simulation for Octopuses {
+    fn step(&mut self) {
+        self.cavern.step();
+        self.last = self.cavern.count(|o| o == (0, false));
+        self.flashes += self.last;
+    }
+
+    fn render(&self) -> String {
+        self.cavern.render(|(e, _)| (e + b'0') as char)
+    }
+
+    fn counters(&self) -> Vec<(&'static str, usize)> {
+        vec![("flashes", self.flashes), ("flashed this step", self.last)]
+    }
+
+    fn snapshot(&self) -> Box<dyn Simulation> {
+        Box::new(self.clone())
+    }
+}
+
 #[aoc(day11, part1, twodee)]
 pub fn part1(input: &str) -> usize {
     let mut grid = parse_input(input);
@@ -207,6 +296,17 @@ pub fn part1_map(input: &str) -> usize {
     (0..100).map(|_| step_map(&mut grid)).sum::<usize>()
 }
 
+#[aoc(day11, part1, ca)]
+pub fn part1_ca(input: &str) -> usize {
+    let mut cavern = cavern(input);
+    (0..100)
+        .map(|_| {
+            cavern.step();
+            cavern.count(|o| o == (0, false))
+        })
+        .sum::<usize>()
+}
+
 #[aoc(day11, part2, twodee)]
 pub fn part2(input: &str) -> usize {
     let mut grid = parse_input(input);
@@ -233,6 +333,20 @@ pub fn part2_map(input: &str) -> usize {
     0
 }
 
+#[aoc(day11, part2, ca)]
+pub fn part2_ca(input: &str) -> usize {
+    let mut cavern = cavern(input);
+    let all = cavern.bounds().area();
+    for i in 1..1000 {
+        cavern.step();
+        if cavern.count(|o| o == (0, false)) == all {
+            return i;
+        }
+    }
+
+    0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -355,12 +469,14 @@ mod tests {
     fn part1_ex1() {
         assert_eq!(part1(EXAMPLE_INPUT), 1656);
         assert_eq!(part1_map(EXAMPLE_INPUT), 1656);
+        assert_eq!(part1_ca(EXAMPLE_INPUT), 1656);
     }
 
     #[test]
     fn part2_ex1() {
         assert_eq!(part2(EXAMPLE_INPUT), 195);
         assert_eq!(part2_map(EXAMPLE_INPUT), 195);
+        assert_eq!(part2_ca(EXAMPLE_INPUT), 195);
     }
 
     #[test]
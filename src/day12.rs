@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::search;
+
 type Edge = (String, String);
 
 #[aoc_generator(day12)]
@@ -18,14 +20,8 @@ type Graph = HashMap<String, Vec<String>>;
 fn to_map(edges: &[Edge]) -> Graph {
     let mut graph: Graph = HashMap::new();
     for (from, to) in edges {
-        graph
-            .entry(from.clone())
-            .or_insert_with(Vec::new)
-            .push(to.clone());
-        graph
-            .entry(to.clone())
-            .or_insert_with(Vec::new)
-            .push(from.clone());
+        graph.entry(from.clone()).or_default().push(to.clone());
+        graph.entry(to.clone()).or_default().push(from.clone());
     }
     graph
 }
@@ -34,114 +30,69 @@ fn is_small(room: &str) -> bool {
     room == room.to_lowercase()
 }
 
-fn explore(graph: Graph, node: String, cpath: Vec<String>) -> u32 {
-    if node == "end" {
-        // println!("PATH: {:?}, {}", cpath, node);
-        return 1;
+fn caves<'a>(graph: &'a Graph) -> impl Fn(&&'a str) -> Vec<(&'a str, u32)> + 'a {
+    |&node| match graph.get(node) {
+        Some(children) => children.iter().map(|c| (c.as_str(), 1)).collect(),
+        None => vec![],
     }
-
-    let mut paths_to_end: u32 = 0;
-
-    if let Some(children) = graph.get(&node) {
-        // println!("NEXT: {:?}", children);
-
-        let mut next_path = cpath.clone();
-        next_path.push(node);
-
-        for child in children {
-            if is_small(child) && cpath.contains(child) {
-                // small room already visited on this path, skip
-                // println!("Path already contains small room {}", child);
-            } else {
-                paths_to_end += explore(graph.clone(), child.clone(), next_path.clone());
-            }
-        }
-    }
-
-    paths_to_end
-}
-
-fn explore_bonus(graph: Graph, node: String, cpath: Vec<String>, bonus: Option<String>) -> u32 {
-    if node == "end" {
-        // println!("PATH: {:?}, {}, BONUS: {:?}", cpath, node, bonus);
-        return 1;
-    }
-
-    let mut paths_to_end: u32 = 0;
-
-    if let Some(children) = graph.get(&node) {
-        // println!("NEXT: {:?}", children);
-
-        let mut next_path = cpath.clone();
-        next_path.push(node);
-
-        for child in children {
-            if is_small(child) {
-                match cpath.iter().filter(|&x| x == child).count() {
-                    0 => {
-                        // Continue, we haven't visited this child yet
-                        paths_to_end += explore_bonus(
-                            graph.clone(),
-                            child.clone(),
-                            next_path.clone(),
-                            bonus.clone(),
-                        );
-                    }
-                    1 => {
-                        if let Some(ref s) = bonus {
-                            if s == child {
-                                // We're the bonus, keep going
-                                paths_to_end += explore_bonus(
-                                    graph.clone(),
-                                    child.clone(),
-                                    next_path.clone(),
-                                    bonus.clone(),
-                                );
-                            } else {
-                                // Another node is the bonus, we're done.
-                            }
-                        } else if child == "start" || child == "end" {
-                            // Can't be bonus, done
-                        } else {
-                            // No bonus picked yet, try this one
-                            paths_to_end += explore_bonus(
-                                graph.clone(),
-                                child.clone(),
-                                next_path.clone(),
-                                Some(child.clone()),
-                            );
-                        }
-                    }
-                    _ => {
-                        // Already visited this child the max # of times
-                    }
-                }
-            } else {
-                paths_to_end += explore_bonus(
-                    graph.clone(),
-                    child.clone(),
-                    next_path.clone(),
-                    bonus.clone(),
-                );
-            }
-        }
-    }
-
-    paths_to_end
 }
 
 #[aoc(day12, part1)]
 pub fn part1(input: &[Edge]) -> u32 {
     let graph = to_map(input);
     // println!("Graph: {:?}", graph);
-    explore(graph, "start".to_string(), vec![])
+    let paths = search::count_paths(
+        &caves(&graph),
+        "start",
+        |&room| room == "end",
+        // small room already visited on this path, skip
+        |path, room| !is_small(room) || !path.contains(room),
+        None,
+    );
+    paths as u32
+}
+
+// A room, and whether some small room has been visited twice on the way
+type Visit<'a> = (&'a str, bool);
+
+// Stepping into a small room may use up the bonus, so offer both
+fn caves_with_bonus<'a>(graph: &'a Graph) -> impl Fn(&Visit<'a>) -> Vec<(Visit<'a>, u32)> + 'a {
+    let caves = caves(graph);
+    move |&(node, used)| {
+        let mut next = vec![];
+        for (room, w) in caves(&node) {
+            next.push(((room, used), w));
+            if !used && is_small(room) {
+                next.push(((room, true), w));
+            }
+        }
+        next
+    }
 }
 
 #[aoc(day12, part2)]
 pub fn part2(input: &[Edge]) -> u32 {
     let graph = to_map(input);
     // println!("Graph: {:?}", graph);
-    explore_bonus(graph, "start".to_string(), vec![], None)
+    let paths = search::count_paths(
+        &caves_with_bonus(&graph),
+        ("start", false),
+        |&(room, _)| room == "end",
+        // One small room (but never start) may be visited twice, which has
+        // to be the step that uses the bonus
+        |path, &(room, used)| {
+            let was_used = path.last().unwrap().1;
+            let revisit = is_small(room) && path.iter().any(|&(r, _)| r == room);
+            let allowed = if revisit {
+                !was_used && used
+            } else {
+                used == was_used
+            };
+            room != "start" && allowed
+        },
+        None,
+    );
+    paths as u32
 }
 
 #[cfg(test)]
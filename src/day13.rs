@@ -1,34 +1,48 @@
 use std::collections::HashSet;
 
+use crate::parse;
+
 type Point = (i32, i32);
 type Fold = (char, i32);
 
-#[derive(Default)]
-struct Manual {
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Manual {
     dots: Vec<Point>,
     folds: Vec<Fold>,
 }
 
-fn parse_input(input: &str) -> Manual {
-    let mut man: Manual = Default::default();
-
-    for line in input.lines() {
-        let l = line.trim();
-        if l.contains(',') {
-            // It's a point
-            let mut pl = l.split(',');
-            let x = pl.next().unwrap().parse::<i32>().unwrap();
-            let y = pl.next().unwrap().parse::<i32>().unwrap();
-            man.dots.push((x, y));
-        } else if l.contains('=') {
-            // It's a fold
-            let mut fl = l[11..].split('=');
-            let axis = fl.next().unwrap().chars().next().unwrap();
-            let val = fl.next().unwrap().parse::<i32>().unwrap();
-            man.folds.push((axis, val));
-        }
+fn axis(inp: &mut parse::Input) -> parse::Result<char> {
+    let start = *inp;
+    match parse::any_char(inp)? {
+        c @ ('x' | 'y') => Ok(c),
+        _ => Err(start.error("x or y", 1)),
     }
-    man
+}
+
+// Dots, a blank line, then folds
+#[aoc_generator(day13)]
+pub fn input_generator(input: &str) -> parse::Result<Manual> {
+    let (dots, folds) = match parse::sections(input)[..] {
+        [dots, folds] => (dots, folds),
+        [_, _, extra, ..] => return Err(extra.error("end of input", 1)),
+        _ => {
+            let mut end = parse::Input::new(input);
+            end.take_while(|_| true);
+            return Err(end.error("a blank line then folds", 0));
+        }
+    };
+
+    let dots = parse::lines(dots, |inp| {
+        let x = parse::int(inp)?;
+        inp.tag(",")?;
+        Ok((x, parse::int(inp)?))
+    })?;
+    let folds = parse::lines(folds, |inp| {
+        inp.tag("fold along ")?;
+        parse::key_value(inp, axis, parse::int)
+    })?;
+
+    Ok(Manual { dots, folds })
 }
 
 fn fold(dots: Vec<Point>, fold: Fold) -> Vec<Point> {
@@ -63,18 +77,19 @@ fn render(dots: &[Point]) {
 }
 
 #[aoc(day13, part1)]
-pub fn part1(input: &str) -> usize {
-    let man = parse_input(input);
-    let dots = fold(man.dots, man.folds[0]);
+pub fn part1(man: &Manual) -> usize {
+    let dots = fold(man.dots.clone(), man.folds[0]);
     dots.len()
 }
 
 #[aoc(day13, part2)]
-pub fn part2(input: &str) -> usize {
-    let mut man = parse_input(input);
-    man.dots = man.folds.iter().fold(man.dots, |dots, &f| fold(dots, f));
-    render(&man.dots);
-    man.dots.len()
+pub fn part2(man: &Manual) -> usize {
+    let dots = man
+        .folds
+        .iter()
+        .fold(man.dots.clone(), |dots, &f| fold(dots, f));
+    render(&dots);
+    dots.len()
 }
 
 #[cfg(test)]
@@ -106,6 +121,19 @@ fold along x=5
 
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(EXAMPLE_INPUT), 17)
+        assert_eq!(part1(&input_generator(EXAMPLE_INPUT).unwrap()), 17)
+    }
+
+    #[test]
+    fn bad_manuals() {
+        let err = input_generator("6,10\n0,14\n").unwrap_err();
+        assert_eq!(err.expected, "a blank line then folds");
+        assert_eq!(err.found, "end of input");
+        let err = input_generator("6,10\n\nfold along z=7\n").unwrap_err();
+        assert_eq!(
+            (err.line, err.col, err.expected.as_str()),
+            (3, 12, "x or y")
+        );
+        assert!(input_generator("6,10\n\nfold along y=7\n\n1,1\n").is_err());
     }
 }
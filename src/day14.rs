@@ -16,7 +16,7 @@ fn parse_input(input: &str) -> Formula {
     lines.next();
     // Then the rules
     for line in lines {
-        let mut l = line.trim().split_whitespace();
+        let mut l = line.split_whitespace();
         let mut from = l.next().unwrap().chars();
         // discard the ->
         l.next();
@@ -45,7 +45,7 @@ fn parse_input_bytes(input: &str) -> FormulaBytes {
     lines.next();
     // Then the rules
     for line in lines {
-        let mut l = line.trim().split_whitespace();
+        let mut l = line.split_whitespace();
         let mut from = l.next().unwrap().bytes();
         // discard the ->
         l.next();
@@ -78,7 +78,7 @@ fn frequencies(polymer: &str) -> Vec<(char, u64)> {
         *freqs.entry(ch).or_insert(0) += 1;
     }
     let mut freqvec: Vec<(char, u64)> = freqs.iter().map(|(&k, &v)| (k, v)).collect();
-    freqvec.sort_by(|a, b| b.1.cmp(&a.1));
+    freqvec.sort_by_key(|b| std::cmp::Reverse(b.1));
     freqvec
 }
 
@@ -120,7 +120,7 @@ fn frequencies_bytes(polymer: &[u8]) -> Vec<(u8, u64)> {
         *freqs.entry(*ch).or_insert(0) += 1;
     }
     let mut freqvec: Vec<(u8, u64)> = freqs.iter().map(|(&k, &v)| (k, v)).collect();
-    freqvec.sort_by(|a, b| b.1.cmp(&a.1));
+    freqvec.sort_by_key(|b| std::cmp::Reverse(b.1));
     freqvec
 }
 
@@ -190,7 +190,7 @@ pub fn part1_pairs(input: &str) -> u64 {
     // println!("Counters: {:?}", counters);
 
     let mut freqs: Vec<(u8, u64)> = counters.iter().map(|(&k, &v)| (k, v)).collect();
-    freqs.sort_by(|a, b| b.1.cmp(&a.1));
+    freqs.sort_by_key(|b| std::cmp::Reverse(b.1));
 
     freqs.first().unwrap().1 - freqs.last().unwrap().1
 }
@@ -227,7 +227,7 @@ pub fn part2_pairs(input: &str) -> u64 {
     // println!("Counters: {:?}", counters);
 
     let mut freqs: Vec<(u8, u64)> = counters.iter().map(|(&k, &v)| (k, v)).collect();
-    freqs.sort_by(|a, b| b.1.cmp(&a.1));
+    freqs.sort_by_key(|b| std::cmp::Reverse(b.1));
 
     freqs.first().unwrap().1 - freqs.last().unwrap().1
 }
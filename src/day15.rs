@@ -1,7 +1,9 @@
-use std::cmp::{max, min, Reverse};
-use std::collections::{BinaryHeap, HashMap};
+use std::cmp::{max, min};
+use std::collections::HashMap;
 use std::ops::RangeInclusive;
 
+use crate::search;
+
 type Point = (usize, usize);
 type Grid = HashMap<Point, u32>;
 
@@ -9,7 +11,7 @@ fn parse_input(input: &str) -> (Grid, usize, usize) {
     let mut grid: Grid = HashMap::new();
     let mut max_r: usize = 0;
     let mut max_c: usize = 0;
-    for (r, row) in input.trim().split_whitespace().enumerate() {
+    for (r, row) in input.split_whitespace().enumerate() {
         // println!("{}, {:?}", r, row);
         max_r = r;
         for (c, ch) in row.trim().bytes().enumerate() {
@@ -38,51 +40,16 @@ fn neighbors((x, y): Point, cols: usize, rows: usize) -> Vec<Point> {
     ns
 }
 
-fn dijkstra(grid: Grid, start: Point, end: Point, cols: usize, rows: usize) -> u32 {
-    // TEXTBOOK?
-
-    let mut dist: HashMap<Point, u32> = HashMap::new();
-    let mut prev: HashMap<Point, Point> = HashMap::new();
-
-    for p in grid.keys() {
-        dist.insert(*p, 10 * 500 * 500);
-    }
-
-    dist.insert(start, 0);
-
-    // Reverse because we need a min-heap
-    let mut pq: BinaryHeap<(Reverse<u32>, Point)> =
-        dist.iter().map(|(&k, &v)| (Reverse(v), k)).collect();
-
-    while !pq.is_empty() {
-        let (_, p) = pq.pop().unwrap();
-        let dist_to_p = *dist.get(&p).unwrap();
-        for n in neighbors(p, cols, rows) {
-            let dist_from_p_to_n = *grid.get(&n).unwrap();
-            let dist_to_n = *dist.get(&n).unwrap();
-            let dist_to_n_via_p = dist_to_p + dist_from_p_to_n;
-            if dist_to_n > dist_to_n_via_p {
-                // Found a shorter path to n via p
-                dist.insert(n, dist_to_n_via_p);
-                prev.insert(n, p);
-                // Need to update the entry for n in the heap,
-                // because we found a shorter path...
-                pq.push((Reverse(dist_to_n_via_p), n));
-            }
-        }
-    }
-
-    // Now read off the prev, starting with the destination
-    let mut cur = end;
-    let mut cost: u32 = 0;
-
-    while cur != start {
-        // println!("Path includes {:?}", cur);
-        cost += *grid.get(&cur).unwrap();
-        cur = *prev.get(&cur).unwrap();
-    }
+fn lowest_risk(grid: &Grid, start: Point, end: Point, cols: usize, rows: usize) -> u32 {
+    // Entering a cell costs its risk level
+    let cave = |&p: &Point| {
+        neighbors(p, cols, rows)
+            .into_iter()
+            .map(|n| (n, grid[&n]))
+            .collect()
+    };
 
-    cost
+    search::dijkstra(&cave, start, |&p| p == end).unwrap().cost
 }
 
 #[allow(dead_code)]
@@ -100,7 +67,7 @@ fn render_map(grid: &Grid, cols: usize, rows: usize) {
 #[aoc(day15, part1)]
 pub fn part1(input: &str) -> u32 {
     let (grid, cols, rows) = parse_input(input);
-    dijkstra(grid, (0, 0), (cols - 1, rows - 1), cols, rows)
+    lowest_risk(&grid, (0, 0), (cols - 1, rows - 1), cols, rows)
 }
 
 #[aoc(day15, part2)]
@@ -127,8 +94,8 @@ pub fn part2(input: &str) -> u32 {
 
     // render_map(&expanded, cols*5, rows*5);
 
-    dijkstra(
-        expanded,
+    lowest_risk(
+        &expanded,
         (0, 0),
         (cols * 5 - 1, rows * 5 - 1),
         cols * 5,
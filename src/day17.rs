@@ -7,51 +7,22 @@ use itertools::Itertools;
 
 use rayon::prelude::*;
 
+use crate::parse;
+
 type Position = (i32, i32);
 type Velocity = (i32, i32);
 type Target = (RangeInclusive<i32>, RangeInclusive<i32>);
 
-fn parse_input(input: &str) -> Target {
-    let mut xrange: RangeInclusive<i32> = 0..=0;
-    let mut yrange: RangeInclusive<i32> = 0..=0;
-    for chunk in input.trim().split_whitespace() {
-        match chunk.chars().next().unwrap() {
-            'x' => {
-                let mut vals = chunk[2..].split("..");
-                let start = vals
-                    .next()
-                    .unwrap()
-                    .trim_matches(',')
-                    .parse::<i32>()
-                    .unwrap();
-                let end = vals
-                    .next()
-                    .unwrap()
-                    .trim_matches(',')
-                    .parse::<i32>()
-                    .unwrap();
-                xrange = start..=end;
-            }
-            'y' => {
-                let mut vals = chunk[2..].split("..");
-                let start = vals
-                    .next()
-                    .unwrap()
-                    .trim_matches(',')
-                    .parse::<i32>()
-                    .unwrap();
-                let end = vals
-                    .next()
-                    .unwrap()
-                    .trim_matches(',')
-                    .parse::<i32>()
-                    .unwrap();
-                yrange = start..=end;
-            }
-            _ => {}
-        }
-    }
-    (xrange, yrange)
+#[aoc_generator(day17)]
+pub fn input_generator(input: &str) -> parse::Result<Target> {
+    parse::complete(input, |inp| {
+        inp.tag("target area: ")?;
+        let xs = parse::named(inp, "x", parse::range)?;
+        inp.tag(",")?;
+        inp.skip_ws();
+        let ys = parse::named(inp, "y", parse::range)?;
+        Ok((xs, ys))
+    })
 }
 
 fn step((x, y): Position, (dx, dy): Velocity) -> (Position, Velocity) {
@@ -86,8 +57,8 @@ fn iterate(v0: Velocity, (tx, ty): Target) -> Option<(Velocity, i32)> {
 }
 
 #[aoc(day17, part1, loop)]
-pub fn part1(input: &str) -> i32 {
-    let (xrange, yrange) = parse_input(input);
+pub fn part1(target: &Target) -> i32 {
+    let (xrange, yrange) = target.clone();
 
     let mut max_y = -100;
 
@@ -103,8 +74,8 @@ pub fn part1(input: &str) -> i32 {
 }
 
 #[aoc(day17, part1, iter)]
-pub fn part1_iter(input: &str) -> i32 {
-    let (xrange, yrange) = parse_input(input);
+pub fn part1_iter(target: &Target) -> i32 {
+    let (xrange, yrange) = target.clone();
     (0..=xrange.clone().max().unwrap())
         .cartesian_product(-100..100)
         .filter_map(|v| iterate(v, (xrange.clone(), yrange.clone())))
@@ -114,8 +85,8 @@ pub fn part1_iter(input: &str) -> i32 {
 }
 
 #[aoc(day17, part1, parallel)]
-pub fn part1_parallel(input: &str) -> i32 {
-    let (xrange, yrange) = parse_input(input);
+pub fn part1_parallel(target: &Target) -> i32 {
+    let (xrange, yrange) = target.clone();
     (0..=xrange.clone().max().unwrap())
         .cartesian_product(-100..100)
         .par_bridge()
@@ -126,15 +97,15 @@ pub fn part1_parallel(input: &str) -> i32 {
 }
 
 #[aoc(day17, part1, geometry)]
-pub fn part1_geometry(input: &str) -> i32 {
-    let (_, yrange) = parse_input(input);
+pub fn part1_geometry(target: &Target) -> i32 {
+    let yrange = target.1.clone();
     let y_min = yrange.min().unwrap().abs();
     (y_min * (y_min - 1)) / 2
 }
 
 #[aoc(day17, part2, loop)]
-pub fn part2(input: &str) -> usize {
-    let (xrange, yrange) = parse_input(input);
+pub fn part2(target: &Target) -> usize {
+    let (xrange, yrange) = target.clone();
 
     let mut ds: HashSet<Velocity> = HashSet::new();
 
@@ -150,8 +121,8 @@ pub fn part2(input: &str) -> usize {
 }
 
 #[aoc(day17, part2, iter)]
-pub fn part2_iter(input: &str) -> usize {
-    let (xrange, yrange) = parse_input(input);
+pub fn part2_iter(target: &Target) -> usize {
+    let (xrange, yrange) = target.clone();
     (0..=xrange.clone().max().unwrap())
         .cartesian_product(-100..100)
         .filter_map(|v| iterate(v, (xrange.clone(), yrange.clone())))
@@ -161,8 +132,8 @@ pub fn part2_iter(input: &str) -> usize {
 }
 
 #[aoc(day17, part2, parallel)]
-pub fn part2_parallel(input: &str) -> usize {
-    let (xrange, yrange) = parse_input(input);
+pub fn part2_parallel(target: &Target) -> usize {
+    let (xrange, yrange) = target.clone();
     (0..=xrange.clone().max().unwrap())
         .cartesian_product(-100..100)
         .par_bridge()
@@ -181,16 +152,20 @@ mod tests {
 
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(EXAMPLE_INPUT), 45);
-        assert_eq!(part1_iter(EXAMPLE_INPUT), 45);
-        assert_eq!(part1_parallel(EXAMPLE_INPUT), 45);
-        assert_eq!(part1_geometry(EXAMPLE_INPUT), 45);
+        let target = input_generator(EXAMPLE_INPUT).unwrap();
+        assert_eq!(part1(&target), 45);
+        assert_eq!(part1_iter(&target), 45);
+        assert_eq!(part1_parallel(&target), 45);
+        assert_eq!(part1_geometry(&target), 45);
+        let err = input_generator("target area: y=-10..-5, x=20..30\n").unwrap_err();
+        assert_eq!((err.col, err.expected.as_str()), (14, "\"x\""));
     }
 
     #[test]
     fn part2_ex1() {
-        assert_eq!(part2(EXAMPLE_INPUT), 112);
-        assert_eq!(part2_iter(EXAMPLE_INPUT), 112);
-        assert_eq!(part2_parallel(EXAMPLE_INPUT), 112);
+        let target = input_generator(EXAMPLE_INPUT).unwrap();
+        assert_eq!(part2(&target), 112);
+        assert_eq!(part2_iter(&target), 112);
+        assert_eq!(part2_parallel(&target), 112);
     }
 }
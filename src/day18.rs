@@ -127,8 +127,8 @@ fn explode(x: SnailFish) -> Option<SnailFish> {
                     if !no_left {
                         before.push_back(stk.pop().unwrap() + left);
                     }
-                    while !stk.is_empty() {
-                        before.push_back(stk.pop().unwrap());
+                    while let Some(element) = stk.pop() {
+                        before.push_back(element);
                     }
 
                     // The pair becomes 0
@@ -274,11 +274,7 @@ fn magnitude(x: SnailFish) -> u64 {
 
 #[aoc(day18, part1)]
 pub fn part1(input: &str) -> u64 {
-    let mut fishes = input
-        .trim()
-        .split_whitespace()
-        .map(parse)
-        .collect::<VecDeque<_>>();
+    let mut fishes = input.split_whitespace().map(parse).collect::<VecDeque<_>>();
 
     let mut val = fishes.pop_front().unwrap();
 
@@ -292,11 +288,7 @@ pub fn part1(input: &str) -> u64 {
 
 #[aoc(day18, part2)]
 pub fn part2(input: &str) -> u64 {
-    let fishes = input
-        .trim()
-        .split_whitespace()
-        .map(parse)
-        .collect::<Vec<_>>();
+    let fishes = input.split_whitespace().map(parse).collect::<Vec<_>>();
 
     let nfish = fishes.len();
 
@@ -1,4 +1,7 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::geom::{Point3, Rotation};
 
 type Position = (i32, i32, i32);
 
@@ -32,249 +35,98 @@ fn parse_input(input: &str) -> VecDeque<Scanner> {
 }
 
 fn permute(s: &Scanner) -> Vec<Scanner> {
-    let mut res: Vec<Scanner> = vec![s.clone()];
-
-    // FORWARDS
-    // XYZ --blue, green, purple
-    // x,-z,y
-    let mut x_nz_y: Scanner = Scanner::new();
-    for p in s.clone() {
-        x_nz_y.insert((p.0, -p.2, p.1));
-    }
-    res.push(x_nz_y);
-
-    // x,-y,-z
-    let mut x_ny_nz: Scanner = Scanner::new();
-    for p in s.clone() {
-        x_ny_nz.insert((p.0, -p.1, -p.2));
-    }
-    res.push(x_ny_nz);
-
-    // x,z,-y
-    let mut x_z_ny: Scanner = Scanner::new();
-    for p in s.clone() {
-        x_z_ny.insert((p.0, p.2, -p.1));
-    }
-    res.push(x_z_ny);
-
-    // BACKWARDS
-    // -x,y,-z
-    let mut nx_y_nz: Scanner = Scanner::new();
-    for p in s.clone() {
-        nx_y_nz.insert((-p.0, p.1, -p.2));
-    }
-    res.push(nx_y_nz);
-
-    // -x,-z,-y
-    let mut nx_nz_ny: Scanner = Scanner::new();
-    for p in s.clone() {
-        nx_nz_ny.insert((-p.0, -p.2, -p.1));
-    }
-    res.push(nx_nz_ny);
-
-    // -x,-y,z
-    let mut nx_ny_z: Scanner = Scanner::new();
-    for p in s.clone() {
-        nx_ny_z.insert((-p.0, -p.1, p.2));
-    }
-    res.push(nx_ny_z);
-
-    // -x,z,y
-    let mut nx_z_y: Scanner = Scanner::new();
-    for p in s.clone() {
-        nx_z_y.insert((-p.0, p.2, p.1));
-    }
-    res.push(nx_z_y);
-
-    // LEFT
-    // y, -x, z
-    let mut y_nx_z: Scanner = Scanner::new();
-    for p in s.clone() {
-        y_nx_z.insert((p.1, -p.0, p.2));
-    }
-    res.push(y_nx_z);
-
-    // -z, -x, y
-    let mut nz_nx_y: Scanner = Scanner::new();
-    for p in s.clone() {
-        nz_nx_y.insert((-p.2, -p.0, p.1));
-    }
-    res.push(nz_nx_y);
-
-    // -y. -x, -z
-    let mut ny_nx_nz: Scanner = Scanner::new();
-    for p in s.clone() {
-        ny_nx_nz.insert((-p.1, -p.0, -p.2));
-    }
-    res.push(ny_nx_nz);
-
-    // z, -x, -y
-    let mut z_nx_ny: Scanner = Scanner::new();
-    for p in s.clone() {
-        z_nx_ny.insert((p.2, -p.0, -p.1));
-    }
-    res.push(z_nx_ny);
-
-    // RIGHT
-    // -y, x, z
-    let mut ny_x_z: Scanner = Scanner::new();
-    for p in s.clone() {
-        ny_x_z.insert((-p.1, p.0, p.2));
-    }
-    res.push(ny_x_z);
-
-    // z, x, y
-    let mut z_x_y: Scanner = Scanner::new();
-    for p in s.clone() {
-        z_x_y.insert((p.2, p.0, p.1));
-    }
-    res.push(z_x_y);
-
-    // y, x, -z
-    let mut y_x_nz: Scanner = Scanner::new();
-    for p in s.clone() {
-        y_x_nz.insert((p.1, p.0, -p.2));
-    }
-    res.push(y_x_nz);
-
-    // -z, x, -y
-    let mut nz_x_ny: Scanner = Scanner::new();
-    for p in s.clone() {
-        nz_x_ny.insert((-p.2, p.0, -p.1));
-    }
-    res.push(nz_x_ny);
-
-    // UP
-    // -z, y, x
-    let mut nz_y_x: Scanner = Scanner::new();
-    for p in s.clone() {
-        nz_y_x.insert((-p.2, p.1, p.0));
-    }
-    res.push(nz_y_x);
-
-    // -y, -z, x
-    let mut ny_nz_x: Scanner = Scanner::new();
-    for p in s.clone() {
-        ny_nz_x.insert((-p.1, -p.2, p.0));
-    }
-    res.push(ny_nz_x);
-
-    // z, -y, x
-    let mut z_ny_x: Scanner = Scanner::new();
-    for p in s.clone() {
-        z_ny_x.insert((p.2, -p.1, p.0));
-    }
-    res.push(z_ny_x);
-
-    // y, z, x
-    let mut y_z_x: Scanner = Scanner::new();
-    for p in s.clone() {
-        y_z_x.insert((p.1, p.2, p.0));
-    }
-    res.push(y_z_x);
-
-    // DOWN
-    // z, y, -x
-    let mut z_y_nx: Scanner = Scanner::new();
-    for p in s.clone() {
-        z_y_nx.insert((p.2, p.1, -p.0));
-    }
-    res.push(z_y_nx);
-
-    // -y, z, -x
-    let mut ny_z_nx: Scanner = Scanner::new();
-    for p in s.clone() {
-        ny_z_nx.insert((-p.1, p.2, -p.0));
-    }
-    res.push(ny_z_nx);
-
-    // -z, -y, -x
-    let mut nz_ny_nx: Scanner = Scanner::new();
-    for p in s.clone() {
-        nz_ny_nx.insert((-p.2, -p.1, -p.0));
-    }
-    res.push(nz_ny_nx);
-
-    // y, -z, -x
-    let mut y_nz_nx: Scanner = Scanner::new();
-    for p in s.clone() {
-        y_nz_nx.insert((p.1, -p.2, -p.0));
-    }
-    res.push(y_nz_nx);
-
-    res
+    Rotation::all()
+        .iter()
+        .map(|r| s.iter().map(|&p| r.apply_point(p.into()).into()).collect())
+        .collect()
 }
 
-fn translate(scanner: &Scanner, zero: Position) -> Scanner {
+fn translate(scanner: &Scanner, delta: Position) -> Scanner {
     // Offset everything in scanner by delta
-    let least = scanner.iter().min().unwrap();
-    let delta = (least.0 - zero.0, least.1 - zero.1, least.2 - zero.2);
     scanner
         .iter()
-        .copied()
-        .map(|(x, y, z)| (x - delta.0, y - delta.1, z - delta.2))
+        .map(|&(x, y, z)| (x + delta.0, y + delta.1, z + delta.2))
         .collect::<Scanner>()
 }
 
-#[aoc(day19, part1)]
-pub fn part1(input: &str) -> usize {
-    let mut puzzle = parse_input(input);
+// Where the scanner sits relative to the known beacons, and its beacons
+// moved there
+fn align(known: &Scanner, scanner: &Scanner) -> Option<(Position, Scanner)> {
+    for perm in permute(scanner) {
+        // Every pairing of a known beacon with one of ours votes for an offset
+        let mut votes: HashMap<Position, usize> = HashMap::new();
+        for k in known {
+            for p in &perm {
+                let delta = (k.0 - p.0, k.1 - p.1, k.2 - p.2);
+                let count = votes.entry(delta).or_insert(0);
+                *count += 1;
+                if *count >= 12 {
+                    return Some((delta, translate(&perm, delta)));
+                }
+            }
+        }
+    }
+    None
+}
 
-    // Start w/ the first scanner's beacons as "known"
-    let mut known_beacons: HashSet<Position> = puzzle
-        .pop_front()
-        .unwrap()
-        .iter()
-        .copied()
-        .collect::<HashSet<_>>();
-    let mut known_scanners: HashSet<Position> = HashSet::new();
-    known_scanners.insert((0, 0, 0));
+// Scanners that never shared enough beacons with the ones already placed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unplaced(pub usize);
 
-    let mut iters = 2;
+impl fmt::Display for Unplaced {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} scanners overlap nothing already placed", self.0)
+    }
+}
 
-    while !puzzle.is_empty() && iters > 0 {
-        iters -= 1;
-        // Is there a permutation / translation that gives us at least 12 points in the known set?
-        let scanner = puzzle.pop_front().unwrap();
-        let mut to_add: Option<Scanner> = None;
-        let mut retry = true;
-        for perm in permute(&scanner) {
-            // Try translating relative to every known beacon
-            println!("Known: {:?}", known_beacons);
-            for beacon in &known_beacons {
-                let xlat = translate(&perm, *beacon);
-                println!("Translated: {:?}", xlat);
-                let intersect = known_beacons.intersection(&xlat).count();
-                println!("INTERSECTION w/ KNOWN: {}", intersect);
-                if intersect >= 12 {
-                    // Found a match!
-                    // Add these (translated) points to the known beacon set
+impl std::error::Error for Unplaced {}
 
-                    to_add = Some(xlat);
-                    retry = false;
-                    break;
-                }
+// Every beacon, and every scanner's position, relative to the first scanner
+fn locate(input: &str) -> Result<(Scanner, Vec<Position>), Unplaced> {
+    let mut puzzle = parse_input(input);
+
+    // Start w/ the first scanner's beacons as "known"
+    let mut known_beacons: Scanner = puzzle.pop_front().unwrap_or_default();
+    let mut known_scanners: Vec<Position> = vec![(0, 0, 0)];
+
+    // Give up once every remaining scanner has been retried without a match
+    let mut stalled = 0;
+    while let Some(scanner) = puzzle.pop_front() {
+        match align(&known_beacons, &scanner) {
+            Some((offset, beacons)) => {
+                known_beacons.extend(beacons);
+                known_scanners.push(offset);
+                stalled = 0;
             }
-            if let Some(beacons) = to_add {
-                for b in beacons {
-                    known_beacons.insert(b);
+            None => {
+                // Try this scanner again later, when we know more.
+                puzzle.push_back(scanner);
+                stalled += 1;
+                if stalled > puzzle.len() {
+                    return Err(Unplaced(puzzle.len()));
                 }
-                break;
             }
         }
-        if retry {
-            // Try this scanner again later, when we know more.
-            puzzle.push_back(scanner);
-        }
     }
 
-    known_beacons.len()
+    Ok((known_beacons, known_scanners))
+}
+
+#[aoc(day19, part1)]
+pub fn part1(input: &str) -> Result<usize, Unplaced> {
+    let (beacons, _) = locate(input)?;
+    Ok(beacons.len())
 }
 
 #[aoc(day19, part2)]
-pub fn part2(input: &str) -> u32 {
-    let puzzle = parse_input(input);
-    0
+pub fn part2(input: &str) -> Result<u32, Unplaced> {
+    let (_, scanners) = locate(input)?;
+    let scanners: Vec<Point3> = scanners.into_iter().map(Point3::from).collect();
+    Ok(scanners
+        .iter()
+        .flat_map(|a| scanners.iter().map(|&b| a.manhattan(b)))
+        .max()
+        .unwrap_or(0))
 }
 
 #[cfg(test)]
@@ -421,11 +273,19 @@ mod tests {
 
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(EXAMPLE_INPUT), 79)
+        assert_eq!(part1(EXAMPLE_INPUT), Ok(79))
     }
 
     #[test]
     fn part2_ex1() {
-        assert_eq!(part2(EXAMPLE_INPUT), 6)
+        assert_eq!(part2(EXAMPLE_INPUT), Ok(3621))
+    }
+
+    #[test]
+    fn unplaced() {
+        // The last scanner shares nothing with the first
+        let far = "--- scanner 0 ---\n0,0,0\n1,2,3\n\n--- scanner 1 ---\n5,5,5\n";
+        assert_eq!(part1(far), Err(Unplaced(1)));
+        assert_eq!(part2(far), Err(Unplaced(1)));
     }
 }
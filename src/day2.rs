@@ -1,65 +1,230 @@
-pub type Instruction = (char, i32);
+use crate::parse::{self, Input};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Forward(i32),
+    Down(i32),
+    Up(i32),
+}
+
+fn command(inp: &mut Input) -> parse::Result<Command> {
+    let start = *inp;
+    let verb = parse::ident(inp)?;
+    let cmd: fn(i32) -> Command = match verb {
+        "forward" => Command::Forward,
+        "down" => Command::Down,
+        "up" => Command::Up,
+        _ => return Err(start.error("forward, down or up", verb.len())),
+    };
+    inp.skip_ws();
+    Ok(cmd(parse::int(inp)?))
+}
 
 #[aoc_generator(day2)]
-pub fn input_generator(input: &str) -> Vec<Instruction> {
-    input
-        .lines()
-        .map(|l| {
-            let mut line = l.trim().split(' ');
-            (
-                line.next().unwrap().chars().next().unwrap(),
-                line.next().unwrap().parse::<i32>().unwrap(),
-            )
-        })
-        .collect()
+pub fn input_generator(input: &str) -> Result<Vec<Command>, parse::ParseError> {
+    parse::lines(input, command)
 }
 
-#[aoc(day2, part1)]
-pub fn part1(input: &[Instruction]) -> i32 {
-    let mut x: i32 = 0;
-    let mut depth: i32 = 0;
-
-    for ins in input {
-        match ins.0 {
-            'u' => {
-                depth -= ins.1;
-            }
-            'd' => {
-                depth += ins.1;
-            }
-            'f' => {
-                x += ins.1;
-            }
-            _ => {}
+// A way of interpreting the commands
+pub trait Submarine {
+    fn apply(&mut self, cmd: Command);
+
+    // Horizontal position and depth
+    fn position(&self) -> (i32, i32);
+
+    fn aim(&self) -> i32 {
+        0
+    }
+
+    // Follow the whole course, returning depth * x
+    fn run(&mut self, course: &[Command]) -> i32 {
+        for &cmd in course {
+            self.apply(cmd);
         }
+        let (x, depth) = self.position();
+        depth * x
     }
+}
 
-    depth * x
+// Up and down move the sub directly
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Direct {
+    pub x: i32,
+    pub depth: i32,
 }
 
-#[aoc(day2, part2)]
-pub fn part2(input: &[Instruction]) -> i32 {
-    let mut x: i32 = 0;
-    let mut depth: i32 = 0;
-    let mut aim: i32 = 0;
-
-    for ins in input {
-        match ins.0 {
-            'u' => {
-                aim -= ins.1;
-            }
-            'd' => {
-                aim += ins.1;
+impl Submarine for Direct {
+    fn apply(&mut self, cmd: Command) {
+        match cmd {
+            Command::Up(n) => self.depth -= n,
+            Command::Down(n) => self.depth += n,
+            Command::Forward(n) => self.x += n,
+        }
+    }
+
+    fn position(&self) -> (i32, i32) {
+        (self.x, self.depth)
+    }
+}
+
+// Up and down tilt the sub, and it dives as it goes forward
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Aimed {
+    pub x: i32,
+    pub depth: i32,
+    pub aim: i32,
+}
+
+impl Submarine for Aimed {
+    fn apply(&mut self, cmd: Command) {
+        match cmd {
+            Command::Up(n) => self.aim -= n,
+            Command::Down(n) => self.aim += n,
+            Command::Forward(n) => {
+                self.x += n;
+                self.depth += self.aim * n;
             }
-            'f' => {
-                x += ins.1;
-                depth += aim * ins.1;
+        }
+    }
+
+    fn position(&self) -> (i32, i32) {
+        (self.x, self.depth)
+    }
+
+    fn aim(&self) -> i32 {
+        self.aim
+    }
+}
+
+// Where the sub was after each command; step 0 is the starting point
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct State {
+    pub step: usize,
+    pub x: i32,
+    pub depth: i32,
+    pub aim: i32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Trajectory {
+    pub states: Vec<State>,
+}
+
+impl Trajectory {
+    pub fn record(sub: &mut impl Submarine, course: &[Command]) -> Trajectory {
+        let state = |step: usize, sub: &dyn Submarine| {
+            let (x, depth) = sub.position();
+            State {
+                step,
+                x,
+                depth,
+                aim: sub.aim(),
             }
-            _ => {}
+        };
+        let mut states = vec![state(0, sub)];
+        for (i, &cmd) in course.iter().enumerate() {
+            sub.apply(cmd);
+            states.push(state(i + 1, sub));
         }
+        Trajectory { states }
     }
 
-    depth * x
+    // The first time the sub was at its deepest
+    pub fn max_depth(&self) -> Option<&State> {
+        self.states.iter().rev().max_by_key(|s| s.depth)
+    }
+
+    // Every time the sub came back up from below the surface
+    pub fn surfacings(&self) -> Vec<&State> {
+        self.states
+            .windows(2)
+            .filter(|w| w[0].depth > 0 && w[1].depth <= 0)
+            .map(|w| &w[1])
+            .collect()
+    }
+
+    pub fn first_reaching(&self, depth: i32) -> Option<&State> {
+        self.states.iter().find(|s| s.depth >= depth)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("step,x,depth,aim\n");
+        for s in &self.states {
+            csv += &format!("{},{},{},{}\n", s.step, s.x, s.depth, s.aim);
+        }
+        csv
+    }
+}
+
+// Make a name safe to drop into SVG text or an attribute
+fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Plot several named trajectories on the same axes, depth growing downwards
+pub fn to_svg(paths: &[(&str, &Trajectory)], width: u32, height: u32) -> String {
+    const COLORS: [&str; 4] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd"];
+
+    let all = paths.iter().flat_map(|(_, t)| t.states.iter());
+    let (min_x, max_x) = all
+        .clone()
+        .fold((0, 1), |(lo, hi), s| (lo.min(s.x), hi.max(s.x)));
+    let (min_d, max_d) = all.fold((0, 1), |(lo, hi), s| (lo.min(s.depth), hi.max(s.depth)));
+    let sx = width as f64 / (max_x - min_x) as f64;
+    let sy = height as f64 / (max_d - min_d) as f64;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = width,
+        h = height
+    );
+    for (i, (name, t)) in paths.iter().enumerate() {
+        let name = escape_xml(name);
+        let points = t
+            .states
+            .iter()
+            .map(|s| {
+                format!(
+                    "{:.1},{:.1}",
+                    (s.x - min_x) as f64 * sx,
+                    (s.depth - min_d) as f64 * sy
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let color = COLORS[i % COLORS.len()];
+        svg += &format!(
+            "  <polyline fill=\"none\" stroke=\"{}\" points=\"{}\"><title>{}</title></polyline>\n",
+            color, points, name
+        );
+        svg += &format!(
+            "  <text x=\"4\" y=\"{}\" fill=\"{}\">{}</text>\n",
+            16 * (i + 1),
+            color,
+            name
+        );
+    }
+    svg + "</svg>\n"
+}
+
+#[aoc(day2, part1)]
+pub fn part1(input: &[Command]) -> i32 {
+    Direct::default().run(input)
+}
+
+#[aoc(day2, part2)]
+pub fn part2(input: &[Command]) -> i32 {
+    Aimed::default().run(input)
 }
 
 #[cfg(test)]
@@ -75,11 +240,44 @@ forward 2
 "#;
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(&input_generator(EXAMPLE_INPUT)), 150)
+        assert_eq!(part1(&input_generator(EXAMPLE_INPUT).unwrap()), 150)
     }
 
     #[test]
     fn part2_ex1() {
-        assert_eq!(part2(&input_generator(EXAMPLE_INPUT)), 900)
+        assert_eq!(part2(&input_generator(EXAMPLE_INPUT).unwrap()), 900)
+    }
+
+    #[test]
+    fn trajectories() {
+        let course = input_generator(EXAMPLE_INPUT).unwrap();
+        let direct = Trajectory::record(&mut Direct::default(), &course);
+        let aimed = Trajectory::record(&mut Aimed::default(), &course);
+
+        assert_eq!(direct.states.len(), 7);
+        assert_eq!(direct.max_depth().unwrap().depth, 10);
+        assert_eq!(aimed.first_reaching(40).unwrap().step, 3);
+        assert!(aimed.surfacings().is_empty());
+        assert_eq!(aimed.to_csv().lines().nth(4), Some("3,13,40,5"));
+
+        let svg = to_svg(&[("direct", &direct), ("aimed", &aimed)], 300, 200);
+        assert_eq!(svg.matches("<polyline").count(), 2);
+
+        let svg = to_svg(&[("<up & \"down\">", &direct)], 300, 200);
+        assert!(svg.contains(">&lt;up &amp; &quot;down&quot;&gt;</text>"));
+    }
+
+    #[test]
+    fn surfacing() {
+        let course = input_generator("down 2\nforward 1\nup 3\nforward 1\n").unwrap();
+        let t = Trajectory::record(&mut Direct::default(), &course);
+        assert_eq!(t.surfacings(), vec![&t.states[3]]);
+    }
+
+    #[test]
+    fn unknown_command() {
+        let err = input_generator("forward 5\nbackward 2\n").unwrap_err();
+        assert_eq!(err.span, 10..18);
+        assert_eq!(err.expected, "forward, down or up");
     }
 }
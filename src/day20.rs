@@ -1,5 +1,8 @@
 use std::collections::{HashMap, HashSet};
 
+use crate::ca;
+use crate::explore::Simulation;
+
 type Point = (i32, i32);
 type Image = HashMap<Point, bool>;
 type Algo = HashSet<u16>;
@@ -10,7 +13,7 @@ fn parse_input(input: &str) -> Puzzle {
     let mut algorithm: Algo = Algo::new();
     let mut image: Image = Image::new();
 
-    let mut lines = input.trim().split_whitespace();
+    let mut lines = input.split_whitespace();
 
     for (i, ch) in lines.next().unwrap().bytes().enumerate() {
         if ch == b'#' {
@@ -121,6 +124,84 @@ fn lit_after_enhance(puzzle: Puzzle, steps: i32) -> usize {
     image.iter().filter(|(_, &v)| v).count()
 }
 
+#[derive(Clone)]
+struct Enhance(Algo);
+
+impl ca::Rule for Enhance {
+    type Cell = bool;
+
+    fn next(&self, _phase: usize, _lit: bool, square: &[bool]) -> bool {
+        let val = square.iter().fold(0_u16, |acc, &b| (acc << 1) | b as u16);
+        self.0.contains(&val)
+    }
+}
+
+fn enhancer(input: &str) -> ca::Automaton<Enhance, ca::Dense<bool>> {
+    let mut lines = input.split_whitespace();
+    let algo: Algo = lines
+        .next()
+        .unwrap()
+        .bytes()
+        .enumerate()
+        .filter(|&(_, ch)| ch == b'#')
+        .map(|(i, _)| i as u16)
+        .collect();
+    let image: ca::Dense<bool> =
+        ca::from_grid(&lines.collect::<Vec<_>>().join("\n"), false, |ch| {
+            ch == b'#'
+        });
+
+    ca::Automaton::new(
+        Enhance(algo),
+        ca::Neighborhood::block(),
+        ca::Boundary::Infinite,
+        false,
+        image,
+    )
+}
+
+fn lit_after_enhance_ca(input: &str, steps: usize) -> usize {
+    let mut enhancer = enhancer(input);
+    enhancer.run(steps);
+    enhancer.count(|lit| lit)
+}
+
+// Steppable view of the image, for the explorer
+#[derive(Clone)]
+pub struct Enhancement {
+    enhancer: ca::Automaton<Enhance, ca::Dense<bool>>,
+}
+
+impl Enhancement {
+    pub fn new(input: &str) -> Enhancement {
+        Enhancement {
+            enhancer: enhancer(input),
+        }
+    }
+}
+
+impl Simulation for Enhancement {
+    fn step(&mut self) {
+        self.enhancer.step();
+    }
+
+    fn render(&self) -> String {
+        self.enhancer.render(|lit| if lit { '#' } else { '.' })
+    }
+
+    fn counters(&self) -> Vec<(&'static str, usize)> {
+        // When the background is lit, only the visible part is counted
+        vec![
+            ("lit pixels", self.enhancer.count(|lit| lit)),
+            ("background lit", self.enhancer.background() as usize),
+        ]
+    }
+
+    fn snapshot(&self) -> Box<dyn Simulation> {
+        Box::new(self.clone())
+    }
+}
+
 #[aoc(day20, part1)]
 pub fn part1(input: &str) -> usize {
     let puzzle = parse_input(input);
@@ -135,6 +216,16 @@ pub fn part2(input: &str) -> usize {
     lit_after_enhance(puzzle, 50)
 }
 
+#[aoc(day20, part1, ca)]
+pub fn part1_ca(input: &str) -> usize {
+    lit_after_enhance_ca(input, 2)
+}
+
+#[aoc(day20, part2, ca)]
+pub fn part2_ca(input: &str) -> usize {
+    lit_after_enhance_ca(input, 50)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,11 +241,13 @@ mod tests {
 
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(EXAMPLE_INPUT), 35)
+        assert_eq!(part1(EXAMPLE_INPUT), 35);
+        assert_eq!(part1_ca(EXAMPLE_INPUT), 35);
     }
 
     #[test]
     fn part2_ex1() {
-        assert_eq!(part2(EXAMPLE_INPUT), 3351)
+        assert_eq!(part2(EXAMPLE_INPUT), 3351);
+        assert_eq!(part2_ca(EXAMPLE_INPUT), 3351);
     }
 }
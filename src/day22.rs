@@ -1,152 +1,103 @@
-use std::cmp::{min, max};
-use std::sync::Arc;
-use itertools::{iproduct, Itertools};
 use fxhash::FxHashMap;
 
-use rayon::prelude::*;
+use crate::geom::{Box3, Point3};
+use crate::parse;
 
-struct Step {
-    on: bool,
-    x_min: i32,
-    x_max: i32,
-    y_min: i32,
-    y_max: i32,
-    z_min: i32,
-    z_max: i32,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub on: bool,
+    pub cuboid: Box3,
 }
 
-type Puzzle = Vec<Step>;
-
-fn parse_input(input: &str) -> Puzzle {
-    input.trim().lines().map(|l| {
-        let mut line = l.split_whitespace();
-        let dir = line.next().unwrap();
-        let mut coords = line.next().unwrap().split(',');
-        let mut xs = coords.next().unwrap().trim_matches(|c| c == 'x' || c == '=').split("..");
-        let x_min = xs.next().unwrap().parse::<i32>().unwrap();
-        let x_max = xs.next().unwrap().parse::<i32>().unwrap();
-        let mut ys = coords.next().unwrap().trim_matches(|c| c == 'y' || c == '=').split("..");
-        let y_min = ys.next().unwrap().parse::<i32>().unwrap();
-        let y_max = ys.next().unwrap().parse::<i32>().unwrap();
-        let mut zs = coords.next().unwrap().trim_matches(|c| c == 'z' || c == '=').split("..");
-        let z_min = zs.next().unwrap().parse::<i32>().unwrap();
-        let z_max = zs.next().unwrap().parse::<i32>().unwrap();
-
-        Step {
-            on: dir == "on",
-            x_min,
-            x_max,
-            y_min,
-            y_max,
-            z_min,
-            z_max,
-        }
-
-    }).collect::<Vec<_>>()
+// on|off x=a..b,y=c..d,z=e..f
+#[aoc_generator(day22)]
+pub fn input_generator(input: &str) -> Result<Vec<Step>, parse::ParseError> {
+    parse::lines(input, |inp| {
+        let start = *inp;
+        let on = match parse::ident(inp)? {
+            "on" => true,
+            "off" => false,
+            dir => return Err(start.error("on or off", dir.len())),
+        };
+        inp.skip_ws();
+        let xs = parse::named(inp, "x", parse::range::<i32>)?;
+        inp.tag(",")?;
+        let ys = parse::named(inp, "y", parse::range::<i32>)?;
+        inp.tag(",")?;
+        let zs = parse::named(inp, "z", parse::range::<i32>)?;
+
+        Ok(Step {
+            on,
+            cuboid: Box3::new(
+                Point3::new(*xs.start(), *ys.start(), *zs.start()),
+                Point3::new(*xs.end(), *ys.end(), *zs.end()),
+            ),
+        })
+    })
 }
 
 #[aoc(day22, part1, naive)]
-pub fn part1(input: &str) -> usize {
-    let puzzle = parse_input(input);
-
+pub fn part1(puzzle: &[Step]) -> usize {
     let mut reactor: FxHashMap<(i32, i32, i32), bool> = FxHashMap::default();
 
+    let init = Box3::new(Point3::new(-50, -50, -50), Point3::new(50, 50, 50));
+
     for step in puzzle {
-        if step.x_min >= -50 && step.x_max <= 50 && step.y_min >= -50 && step.y_max <= 50 && step.z_min >= -50 && step.z_max <= 50 {
-            for x in step.x_min..=step.x_max {
-                for y in step.y_min..=step.y_max {
-                    for z in step.z_min..=step.z_max {
-                        reactor.insert((x,y,z), step.on);
-                    }
-                }
+        if init.encloses(&step.cuboid) {
+            for p in step.cuboid.points() {
+                reactor.insert(p.into(), step.on);
             }
         }
     }
     reactor.values().filter(|&v| *v).count()
 }
 
-fn eval_range(range: Step, steps: &[Step]) -> u64 {
+fn eval_range(range: Box3, steps: &[Step]) -> u64 {
     let mut reactor: FxHashMap<(i32, i32, i32), bool> = FxHashMap::default();
 
     for step in steps {
-
         // what part of this step falls in our range?
-        if step.x_max < range.x_min || step.x_min > range.x_max {
-            continue;
-        }
-        if step.y_max < range.y_min || step.y_min > range.y_max {
-            continue;
-        }
-        if step.z_max < range.z_min || step.z_min > range.z_max {
-            continue;
-        }
-
-        let x_min = max(step.x_min, range.x_min);
-        let x_max = min(step.x_max, range.x_max);
-        let y_min = max(step.y_min, range.y_min);
-        let y_max = min(step.y_max, range.y_max);
-        let z_min = max(step.z_min, range.z_min);
-        let z_max = min(step.z_max, range.z_max);
+        let overlap = match step.cuboid.intersection(&range) {
+            Some(overlap) => overlap,
+            None => continue,
+        };
 
         // iproduct!(x_min..=x_max, y_min..=y_max, z_min..=z_max).for_each(|(x,y,z)| {
         //     reactor.insert((x,y,z), step.on);
         // });
-        for x in x_min..=x_max {
-            for y in y_min..=y_max {
-                for z in z_min..=z_max {
-                    reactor.insert((x,y,z), step.on);
-                }
-            }
+        for p in overlap.points() {
+            reactor.insert(p.into(), step.on);
         }
     }
     reactor.values().filter(|&v| *v).count() as u64
 }
 
 #[aoc(day22, part1, blocks)]
-pub fn part1_blocks(input: &str) -> u64 {
-    let puzzle = parse_input(input);
-
-    let range = Step {
-        on: true,
-        x_min: -50,
-        x_max: 50,
-        y_min: -50,
-        y_max: 50,
-        z_min: -50,
-        z_max: 50,
-    };
-
-    eval_range(range, &puzzle)
+pub fn part1_blocks(puzzle: &[Step]) -> u64 {
+    let range = Box3::new(Point3::new(-50, -50, -50), Point3::new(50, 50, 50));
+
+    eval_range(range, puzzle)
 }
 
+// Inclusion-exclusion over signed cuboids. Each step cancels its overlap
+// with everything counted so far, then counts itself if it's on.
 #[aoc(day22, part2)]
-pub fn part2(input: &str) -> u64 {
-    let puzzle = parse_input(input);
-
-    // Computing power hasn't advanced *that* far yet.
-    // for step in puzzle {
-    //     for x in step.x_min..=step.x_max {
-    //         for y in step.y_min..=step.y_max {
-    //             for z in step.z_min..=step.z_max {
-    //                 reactor.insert((x,y,z), step.on);
-    //             }
-    //         }
-    //     }
-    // }
-
-    // iproduct!(-120..=120,-120..=120,-120..=120).par_bridge().map(|(x,y,z)| {
-    //     let range = Step {
-    //         on: true,
-    //         x_min: x * 1000,
-    //         x_max: ((x+1) * 1000) - 1,
-    //         y_min: y * 1000,
-    //         y_max: ((y+1) * 1000) - 1,
-    //         z_min: z * 1000,
-    //         z_max: ((z+1) * 1000) - 1,
-    //     };
-    //     eval_range(range, &puzzle)
-    // }).sum()
-    0
+pub fn part2(puzzle: &[Step]) -> u64 {
+    let mut signed: Vec<(Box3, i64)> = vec![];
+    for step in puzzle {
+        let overlaps: Vec<(Box3, i64)> = signed
+            .iter()
+            .filter_map(|(b, sign)| b.intersection(&step.cuboid).map(|o| (o, -sign)))
+            .collect();
+        signed.extend(overlaps);
+        if step.on {
+            signed.push((step.cuboid, 1));
+        }
+    }
+    signed
+        .iter()
+        .map(|(b, sign)| b.volume() as i64 * sign)
+        .sum::<i64>() as u64
 }
 
 #[cfg(test)]
@@ -241,13 +192,38 @@ off x=-93533..-4276,y=-16170..68771,z=-104985..-24507
 
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(EXAMPLE_INPUT), 590784);
-        assert_eq!(part1_blocks(EXAMPLE_INPUT), 590784);
-        assert_eq!(part1(EXAMPLE_2), 474140);
+        let puzzle = input_generator(EXAMPLE_INPUT).unwrap();
+        assert_eq!(part1(&puzzle), 590784);
+        assert_eq!(part1_blocks(&puzzle), 590784);
+        assert_eq!(part1(&input_generator(EXAMPLE_2).unwrap()), 474140);
+    }
+
+    #[test]
+    fn bad_steps() {
+        let err =
+            input_generator("on x=1..2,y=1..2,z=1..2\ntoggle x=1..2,y=1..2,z=1..2\n").unwrap_err();
+        assert_eq!((err.line, err.col), (2, 1));
+        assert_eq!(err.expected, "on or off");
+        let err = input_generator("on x=1..2,z=1..2,y=1..2\n").unwrap_err();
+        assert_eq!((err.expected.as_str(), err.col), ("\"y\"", 11));
+        assert!(input_generator("off x=1..2,y=1..2\n").is_err());
+        assert!(input_generator("off x=1..2,y=1..2,z=1..2,w=1..2\n").is_err());
     }
 
     #[test]
     fn part2_ex1() {
-        assert_eq!(part2(EXAMPLE_2), 2758514936282235);
+        assert_eq!(
+            part2(&input_generator(EXAMPLE_2).unwrap()),
+            2758514936282235
+        );
+        // Restricted to the initialisation area it agrees with part 1
+        let puzzle = input_generator(EXAMPLE_INPUT).unwrap();
+        let init = Box3::new(Point3::new(-50, -50, -50), Point3::new(50, 50, 50));
+        let inside: Vec<Step> = puzzle
+            .iter()
+            .filter(|s| init.encloses(&s.cuboid))
+            .copied()
+            .collect();
+        assert_eq!(part2(&inside), 590784);
     }
 }
@@ -1,5 +1,10 @@
+use std::fmt;
+
 use fxhash::FxHashSet;
 
+use crate::ca;
+use crate::explore::Simulation;
+
 type Point = (u8, u8);
 
 #[derive(Debug, PartialEq)]
@@ -15,7 +20,7 @@ fn parse_input(input: &str) -> Puzzle {
     let mut south: FxHashSet<Point> = FxHashSet::default();
     let mut max_r: usize = 0;
     let mut max_c: usize = 0;
-    for (r, row) in input.trim().split_whitespace().enumerate() {
+    for (r, row) in input.split_whitespace().enumerate() {
         // println!("{}, {:?}", r, row);
         max_r = r;
         for (c, ch) in row.trim().bytes().enumerate() {
@@ -129,6 +134,103 @@ pub fn part1(input: &str) -> u32 {
     steps
 }
 
+#[derive(Clone)]
+struct Herds;
+
+impl ca::Rule for Herds {
+    type Cell = u8;
+
+    // Neighbours are N, E, S, W
+    fn next(&self, phase: usize, cell: u8, n: &[u8]) -> u8 {
+        match (phase, cell) {
+            // East-facing herd moves first, then the south-facing one
+            (0, b'.') if n[3] == b'>' => b'>',
+            (0, b'>') if n[1] == b'.' => b'.',
+            (1, b'.') if n[0] == b'v' => b'v',
+            (1, b'v') if n[2] == b'.' => b'.',
+            _ => cell,
+        }
+    }
+
+    fn phases(&self) -> usize {
+        2
+    }
+}
+
+fn trench(input: &str) -> ca::Automaton<Herds, ca::Sparse<u8>> {
+    let cells = ca::from_grid(input, b'.', |ch| ch);
+    ca::Automaton::new(
+        Herds,
+        ca::Neighborhood::von_neumann(),
+        ca::Boundary::Toroidal,
+        b'.',
+        cells,
+    )
+}
+
+// The herds kept moving, either round in a cycle or past the step limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NeverStops(pub ca::Outcome);
+
+impl fmt::Display for NeverStops {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            ca::Outcome::Cycle { start, period } => {
+                write!(f, "herds repeat every {} steps from step {}", period, start)
+            }
+            _ => write!(f, "herds still moving at the step limit"),
+        }
+    }
+}
+
+impl std::error::Error for NeverStops {}
+
+#[aoc(day25, part1, ca)]
+pub fn part1_ca(input: &str) -> Result<usize, NeverStops> {
+    match trench(input).run_until_stable(usize::MAX) {
+        ca::Outcome::FixedPoint { generation } => Ok(generation),
+        other => Err(NeverStops(other)),
+    }
+}
+
+// Steppable view of the trench, for the explorer
+#[derive(Clone)]
+pub struct Trench {
+    trench: ca::Automaton<Herds, ca::Sparse<u8>>,
+    moves: usize,
+    last: usize,
+}
+
+impl Trench {
+    pub fn new(input: &str) -> Trench {
+        Trench {
+            trench: trench(input),
+            moves: 0,
+            last: 0,
+        }
+    }
+}
+
+impl Simulation for Trench {
+    fn step(&mut self) {
+        // Each move empties one cell and fills another
+        self.last = self.trench.step() / 2;
+        self.moves += self.last;
+    }
+
+    fn render(&self) -> String {
+        self.trench.render(|c| c as char)
+    }
+
+    fn counters(&self) -> Vec<(&'static str, usize)> {
+        vec![("moves", self.moves), ("moved this step", self.last)]
+    }
+
+    fn snapshot(&self) -> Box<dyn Simulation> {
+        Box::new(self.clone())
+    }
+}
+
 // #[aoc(day25, part2)]
 // pub fn part2(input: &str) -> u32 {
 //     let puzzle = parse_input(input);
@@ -163,7 +265,16 @@ vv...>>vv.
 
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(EXAMPLE_INPUT), 58)
+        assert_eq!(part1(EXAMPLE_INPUT), 58);
+        assert_eq!(part1_ca(EXAMPLE_INPUT), Ok(58));
+        // A lone cucumber goes round its row forever
+        assert_eq!(
+            part1_ca(">..\n"),
+            Err(NeverStops(ca::Outcome::Cycle {
+                start: 0,
+                period: 3
+            }))
+        );
     }
 
     #[test]
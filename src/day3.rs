@@ -1,87 +1,294 @@
-const BITS: usize = 5;
-//const BITS: usize = 12;
+use std::cmp::Ordering;
+use std::fmt;
 
+use crate::parse;
+
+// Widest diagnostic we can hold
+pub const MAX_BITS: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub width: usize,
+    pub values: Vec<u128>,
+}
+
+// Every line must be the same number of bits
 #[aoc_generator(day3)]
-pub fn input_generator(input: &str) -> Vec<u32> {
-    input
-        .lines()
-        .map(|l| u32::from_str_radix(l.trim(), 2).unwrap())
-        .collect()
+pub fn input_generator(input: &str) -> Result<Report, parse::ParseError> {
+    let mut width: Option<usize> = None;
+    let values = parse::lines(input, |inp| {
+        let start = *inp;
+        let bits = inp.take_while(|c| c == '0' || c == '1');
+        let w = *width.get_or_insert(bits.len());
+        if bits.is_empty() || bits.len() != w || w > MAX_BITS {
+            return Err(start.error(format!("{} binary digits", w.min(MAX_BITS)), bits.len()));
+        }
+        Ok(u128::from_str_radix(bits, 2).unwrap())
+    })?;
+
+    Ok(Report {
+        width: width.unwrap_or(0),
+        values,
+    })
+}
+
+// Two ratings whose product doesn't fit in 128 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow(pub u128, pub u128);
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} * {} overflows 128 bits", self.0, self.1)
+    }
+}
+
+impl std::error::Error for Overflow {}
+
+// Past 64 bits the product may no longer fit, but the ratings themselves do
+fn product(a: u128, b: u128) -> Result<u128, Overflow> {
+    a.checked_mul(b).ok_or(Overflow(a, b))
 }
 
 #[aoc(day3, part1)]
-pub fn part1(input: &[u32]) -> u32 {
+pub fn part1(report: &Report) -> Result<u128, Overflow> {
+    let (gamma, epsilon) = rates(&report.values, report.width);
+    product(gamma, epsilon)
+}
+
+// Gamma and epsilon rates
+pub fn rates(input: &[u128], width: usize) -> (u128, u128) {
     let mut gamma = 0;
     let mut epsilon = 0;
 
-    for i in 0..BITS {
+    for i in 0..width {
         let mcb = most_common_bit_at_index(input, i);
-        println!("{} {}", i, mcb);
         gamma += mcb << i;
         epsilon += (1 - mcb) << i;
     }
 
-    println!("ga: {}, ep: {}", gamma, epsilon);
-
-    epsilon * gamma
+    (gamma, epsilon)
 }
 
-fn most_common_bit_at_index(input: &[u32], index: usize) -> u32 {
-    let ones: usize = input
-        .iter()
-        .map(|n| -> u32 { (n & (1 << index) > 0).into() })
-        .sum::<u32>() as usize;
-    // println!("MCB @ {}: {}, {}", index, input.len(), ones);
-    let threshold = (1 + input.len()) / 2;
+fn most_common_bit_at_index(input: &[u128], index: usize) -> u128 {
+    let ones: usize = input.iter().filter(|&n| n & (1 << index) > 0).count();
+    let threshold = input.len().div_ceil(2);
     (ones >= threshold).into()
 }
 
-fn filter_most_common_at_index(input: &[u32], index: usize) -> Vec<u32> {
-    let mcb = most_common_bit_at_index(input, index);
-    // println!("MCB @ {} = {}", index, mcb);
-    input
-        .iter()
-        .filter(|&v| ((v & (1 << index)) ^ (mcb << index)) == 0)
-        .copied()
-        .collect::<Vec<_>>()
+// Which half of the remaining values survives a round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    MostCommon,
+    LeastCommon,
 }
 
-fn filter_least_common_at_index(input: &[u32], index: usize) -> Vec<u32> {
-    let lcb = 1 - most_common_bit_at_index(input, index);
-    // println!("LCB @ {} = {}", index, lcb);
-    input
-        .iter()
-        .filter(|&v| ((v & (1 << index)) ^ (lcb << index)) == 0)
-        .copied()
-        .collect::<Vec<_>>()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanOrder {
+    MsbFirst,
+    LsbFirst,
 }
 
-#[aoc(day3, part2)]
-pub fn part2(input: &[u32]) -> u32 {
-    let mut remaining = input.iter().copied().collect::<Vec<u32>>();
-    let mut oxygen: u32 = 0;
-    let mut co2: u32 = 0;
-    for i in 1..=BITS {
-        remaining = filter_most_common_at_index(&remaining, BITS - i);
-        // println!("Remaining: {:?}", remaining);
-        if remaining.len() == 1 {
-            oxygen = remaining[0];
+// How a rating narrows the report down, one bit at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitCriteria {
+    pub keep: Keep,
+    // Keep the ones when there are as many ones as zeros
+    pub ones_on_tie: bool,
+    pub order: ScanOrder,
+}
+
+impl BitCriteria {
+    pub const OXYGEN: BitCriteria = BitCriteria {
+        keep: Keep::MostCommon,
+        ones_on_tie: true,
+        order: ScanOrder::MsbFirst,
+    };
+
+    pub const CO2: BitCriteria = BitCriteria {
+        keep: Keep::LeastCommon,
+        ones_on_tie: false,
+        order: ScanOrder::MsbFirst,
+    };
+
+    // The bit to keep, given how many of each there are. A bit nobody has is
+    // never picked, so the values can't all be eliminated.
+    pub fn pick(&self, zeros: usize, ones: usize) -> u8 {
+        if zeros == 0 {
+            return 1;
+        }
+        if ones == 0 {
+            return 0;
+        }
+        match (self.keep, ones.cmp(&zeros)) {
+            (_, Ordering::Equal) => self.ones_on_tie as u8,
+            (Keep::MostCommon, ord) => (ord == Ordering::Greater) as u8,
+            (Keep::LeastCommon, ord) => (ord == Ordering::Less) as u8,
+        }
+    }
+
+    fn bits(&self, width: usize) -> Box<dyn Iterator<Item = usize>> {
+        match self.order {
+            ScanOrder::MsbFirst => Box::new((0..width).rev()),
+            ScanOrder::LsbFirst => Box::new(0..width),
+        }
+    }
+}
+
+// One bit's worth of filtering
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Round {
+    pub bit: usize,
+    pub zeros: usize,
+    pub ones: usize,
+    pub kept: u8,
+    pub eliminated: Vec<u128>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rating {
+    // None only for an empty report
+    pub value: Option<u128>,
+    pub trace: Vec<Round>,
+}
+
+// Filter the report down to one value, recording what went at each bit
+pub fn rating(input: &[u128], width: usize, criteria: &BitCriteria) -> Rating {
+    let mut remaining = input.to_vec();
+    let mut trace = vec![];
+    for bit in criteria.bits(width) {
+        if remaining.len() <= 1 {
             break;
         }
+        let ones = remaining.iter().filter(|&v| v & (1 << bit) != 0).count();
+        let zeros = remaining.len() - ones;
+        let kept = criteria.pick(zeros, ones);
+        let (keep, eliminated) = remaining
+            .into_iter()
+            .partition(|&v| (v >> bit) & 1 == kept as u128);
+        remaining = keep;
+        trace.push(Round {
+            bit,
+            zeros,
+            ones,
+            kept,
+            eliminated,
+        });
+    }
+    Rating {
+        value: remaining.first().copied(),
+        trace,
+    }
+}
+
+#[aoc(day3, part2)]
+pub fn part2(report: &Report) -> Result<u128, Overflow> {
+    life_support(&report.values, report.width)
+}
+
+fn life_support(input: &[u128], width: usize) -> Result<u128, Overflow> {
+    let oxygen = rating(input, width, &BitCriteria::OXYGEN)
+        .value
+        .unwrap_or(0);
+    let co2 = rating(input, width, &BitCriteria::CO2).value.unwrap_or(0);
+    product(oxygen, co2)
+}
+
+// Count the ones in every column at once. planes[j] holds bit j of each
+// column's count, so adding a value is a ripple-carry add across the planes.
+pub fn column_counts(input: &[u128], width: usize) -> Vec<usize> {
+    let mut planes: Vec<u128> = vec![];
+    for &v in input {
+        let mut carry = v;
+        for plane in planes.iter_mut() {
+            if carry == 0 {
+                break;
+            }
+            let sum = *plane ^ carry;
+            carry &= *plane;
+            *plane = sum;
+        }
+        if carry != 0 {
+            planes.push(carry);
+        }
     }
 
-    remaining = input.iter().copied().collect::<Vec<u32>>();
-    for i in 1..=BITS {
-        remaining = filter_least_common_at_index(&remaining, BITS - i);
-        // println!("Remaining: {:?}", remaining);
-        if remaining.len() == 1 {
-            co2 = remaining[0];
+    (0..width)
+        .map(|i| {
+            planes
+                .iter()
+                .enumerate()
+                .map(|(j, plane)| (((plane >> i) & 1) as usize) << j)
+                .sum()
+        })
+        .collect()
+}
+
+pub fn rates_bitsliced(input: &[u128], width: usize) -> (u128, u128) {
+    let mut gamma = 0;
+    for (i, ones) in column_counts(input, width).into_iter().enumerate() {
+        // Ties go to 1
+        if ones * 2 >= input.len() {
+            gamma |= 1 << i;
+        }
+    }
+    let mask = if width == MAX_BITS {
+        u128::MAX
+    } else {
+        (1 << width) - 1
+    };
+    (gamma, !gamma & mask)
+}
+
+// Move everything with the bit clear to the front, returning how many there are
+fn partition_by_bit(values: &mut [u128], bit: usize) -> usize {
+    let mut zeros = 0;
+    for i in 0..values.len() {
+        if values[i] & (1 << bit) == 0 {
+            values.swap(zeros, i);
+            zeros += 1;
+        }
+    }
+    zeros
+}
+
+// Narrow values down in place by the same criteria as rating, moving the
+// kept half of what's left to one end at each bit. None only for an empty
+// report.
+fn rating_in_place(values: &mut [u128], width: usize, criteria: &BitCriteria) -> Option<u128> {
+    let (mut lo, mut hi) = (0, values.len());
+    for bit in criteria.bits(width) {
+        if hi - lo <= 1 {
             break;
         }
+        let zeros = partition_by_bit(&mut values[lo..hi], bit);
+        if criteria.pick(zeros, hi - lo - zeros) == 1 {
+            lo += zeros;
+        } else {
+            hi = lo + zeros;
+        }
     }
+    values[lo..hi].first().copied()
+}
+
+// Oxygen and CO2 ratings, reusing one buffer for both
+pub fn life_support_in_place(input: &[u128], width: usize) -> (Option<u128>, Option<u128>) {
+    let mut buf = input.to_vec();
+    let oxygen = rating_in_place(&mut buf, width, &BitCriteria::OXYGEN);
+    buf.copy_from_slice(input);
+    let co2 = rating_in_place(&mut buf, width, &BitCriteria::CO2);
+    (oxygen, co2)
+}
+
+#[aoc(day3, part1, bitsliced)]
+pub fn part1_bitsliced(report: &Report) -> Result<u128, Overflow> {
+    let (gamma, epsilon) = rates_bitsliced(&report.values, report.width);
+    product(gamma, epsilon)
+}
 
-    println!("O2 {} CO2 {}", oxygen, co2);
-    oxygen * co2
+#[aoc(day3, part2, partition)]
+pub fn part2_partition(report: &Report) -> Result<u128, Overflow> {
+    let (oxygen, co2) = life_support_in_place(&report.values, report.width);
+    product(oxygen.unwrap_or(0), co2.unwrap_or(0))
 }
 
 #[cfg(test)]
@@ -103,11 +310,105 @@ mod tests {
 "#;
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(&input_generator(EXAMPLE_INPUT)), 198)
+        assert_eq!(part1(&input_generator(EXAMPLE_INPUT).unwrap()), Ok(198))
     }
 
     #[test]
     fn part2_ex1() {
-        assert_eq!(part2(&input_generator(EXAMPLE_INPUT)), 230)
+        assert_eq!(part2(&input_generator(EXAMPLE_INPUT).unwrap()), Ok(230))
+    }
+
+    #[test]
+    fn bit_parallel() {
+        let report = input_generator(EXAMPLE_INPUT).unwrap();
+        assert_eq!(column_counts(&report.values, 5), vec![5, 7, 8, 5, 7]);
+        assert_eq!(part1_bitsliced(&report), Ok(198));
+        assert_eq!(part2_partition(&report), Ok(230));
+
+        // Pseudo-random 12 bit report, checked against the straightforward way
+        let values: Vec<u128> = (0..5000_u128).map(|i| (i * 2654435761) % 4096).collect();
+        assert_eq!(rates_bitsliced(&values, 12), rates(&values, 12));
+        assert_eq!(
+            life_support_in_place(&values, 12),
+            (
+                rating(&values, 12, &BitCriteria::OXYGEN).value,
+                rating(&values, 12, &BitCriteria::CO2).value
+            )
+        );
+    }
+
+    #[test]
+    fn constant_columns() {
+        // Every value agrees on the top bit, and on the bottom one too below
+        for input in [
+            "100\n101\n111\n",
+            "0100\n0101\n0111\n",
+            "1001\n1011\n1111\n",
+        ] {
+            let report = input_generator(input).unwrap();
+            let expected = part2(&report).unwrap();
+            assert_eq!(part2_partition(&report), Ok(expected), "{:?}", input);
+        }
+        assert_eq!(part2(&input_generator("100\n101\n111\n").unwrap()), Ok(35));
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(input_generator(EXAMPLE_INPUT).unwrap().width, 5);
+
+        let wide = input_generator(&format!("{}\n{}\n", "1".repeat(128), "0".repeat(128))).unwrap();
+        assert_eq!(wide.width, 128);
+        assert_eq!(rates(&wide.values, wide.width), (u128::MAX, 0));
+        assert_eq!(part1(&wide), Ok(0));
+
+        // Half ones and half zeros, so both rates are about 2^127
+        let half = format!("{}{}", "1".repeat(64), "0".repeat(64));
+        let wide = input_generator(&format!("{}\n{}\n{}\n", half, half, "0".repeat(128))).unwrap();
+        let gamma = u128::MAX << 64;
+        assert_eq!(part1(&wide), Err(Overflow(gamma, !gamma)));
+        assert!(part1_bitsliced(&wide).is_err());
+
+        let high = format!("11{}", "0".repeat(126));
+        let low = format!("10{}", "1".repeat(126));
+        let wide = input_generator(&format!("{}\n{}\n{}\n", high, high, low)).unwrap();
+        let (oxygen, co2) = (3 << 126, (1 << 127) | (u128::MAX >> 2));
+        assert_eq!(part2(&wide), Err(Overflow(oxygen, co2)));
+        assert_eq!(part2_partition(&wide), Err(Overflow(oxygen, co2)));
+
+        let err = input_generator("00100\n1111\n").unwrap_err();
+        assert_eq!(err.line, 2);
+        assert_eq!(err.expected, "5 binary digits");
+        assert!(input_generator(&"1".repeat(129)).is_err());
+    }
+
+    #[test]
+    fn rating_trace() {
+        let report = input_generator(EXAMPLE_INPUT).unwrap();
+        let oxygen = rating(&report.values, 5, &BitCriteria::OXYGEN);
+        assert_eq!(oxygen.value, Some(0b10111));
+        assert_eq!(oxygen.trace.len(), 5);
+        assert_eq!((oxygen.trace[0].zeros, oxygen.trace[0].ones), (5, 7));
+        assert_eq!(oxygen.trace[0].kept, 1);
+        assert_eq!(oxygen.trace[0].eliminated.len(), 5);
+        let co2 = rating(&report.values, 5, &BitCriteria::CO2);
+        assert_eq!(co2.value, Some(0b01010));
+        assert_eq!(co2.trace.len(), 3);
+
+        // Every value is either eliminated once or is the rating
+        let eliminated: usize = co2.trace.iter().map(|r| r.eliminated.len()).sum();
+        assert_eq!(eliminated + 1, report.values.len());
+
+        // Least common from the low end, ties going to ones
+        let lsb = BitCriteria {
+            keep: Keep::LeastCommon,
+            ones_on_tie: true,
+            order: ScanOrder::LsbFirst,
+        };
+        let r = rating(&report.values, 5, &lsb);
+        assert_eq!(r.trace[0].bit, 0);
+        assert_eq!(r.trace[0].kept, 1);
+        assert_eq!(rating(&[], 5, &lsb).value, None);
+        assert_eq!(life_support_in_place(&[], 5), (None, None));
+        assert_eq!(part2_partition(&input_generator("").unwrap()), Ok(0));
     }
 }
@@ -1,149 +1,310 @@
-#[derive(Debug, Clone)]
+use fxhash::FxHashMap;
+
+use crate::parse;
+
+// Which lines win, beyond the usual rows and columns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rules {
+    pub diagonals: bool,
+    // The middle square starts marked and doesn't score (odd sizes only)
+    pub free_centre: bool,
+    pub four_corners: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Board {
     cells: Vec<u8>,
-    score: u32,
+    size: usize,
 }
 
-impl Default for Board {
-    fn default() -> Self {
-        Self::new()
+impl Board {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn cells(&self) -> &[u8] {
+        &self.cells
+    }
+
+    fn centre(&self, rules: &Rules) -> Option<usize> {
+        if rules.free_centre && self.size % 2 == 1 {
+            Some(self.cells.len() / 2)
+        } else {
+            None
+        }
+    }
+
+    // Cell indexes of every line that wins under the rules
+    fn lines(&self, rules: &Rules) -> Vec<Vec<usize>> {
+        let n = self.size;
+        let mut lines = vec![];
+        for r in 0..n {
+            lines.push((0..n).map(|c| r * n + c).collect());
+        }
+        for c in 0..n {
+            lines.push((0..n).map(|r| r * n + c).collect());
+        }
+        if rules.diagonals {
+            lines.push((0..n).map(|i| i * n + i).collect());
+            lines.push((0..n).map(|i| i * n + n - 1 - i).collect());
+        }
+        if rules.four_corners {
+            lines.push(vec![0, n - 1, n * (n - 1), n * n - 1]);
+        }
+        lines
     }
 }
 
-impl Board {
-    pub fn new() -> Self {
-        Board {
-            score: 0,
-            cells: Vec::with_capacity(25),
+// The draw and the boards, before anything is played
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bingo {
+    pub calls: Vec<u8>,
+    pub boards: Vec<Board>,
+}
+
+fn board(section: parse::Input) -> parse::Result<Board> {
+    let mut size: Option<usize> = None;
+    let mut rows = 0;
+    let mut cells = vec![];
+    let mut last = section;
+    parse::lines(section, |inp| {
+        last = *inp;
+        let start = *inp;
+        let row = parse::sep_by(inp, parse::int::<u8>, " ")?;
+        let n = *size.get_or_insert(row.len());
+        if row.len() != n {
+            return Err(start.error(format!("a row of {} numbers", n), inp.pos() - start.pos()));
         }
+        rows += 1;
+        cells.extend(row);
+        Ok(())
+    })?;
+    let size = size.unwrap_or(0);
+    if rows != size {
+        return Err(last.error(format!("{} rows", size), 1));
     }
+    Ok(Board { cells, size })
+}
+
+// Calls on the first line, then blank-line separated square boards
+#[aoc_generator(day4)]
+pub fn input_generator(input: &str) -> Result<Bingo, parse::ParseError> {
+    let mut sections = parse::sections(input).into_iter();
+    let calls = match sections.next() {
+        Some(first) => parse::lines(first, |i| parse::sep_by(i, parse::int::<u8>, ","))?
+            .into_iter()
+            .flatten()
+            .collect(),
+        None => vec![],
+    };
+    let boards = sections.map(board).collect::<Result<_, _>>()?;
+    Ok(Bingo { calls, boards })
+}
 
-    fn winp(&self, marked: &[u8]) -> bool {
-        // Rows
-        for r in 0..5 {
-            let offset = r * 5;
-            let row = [
-                self.cells[offset],
-                self.cells[offset + 1],
-                self.cells[offset + 2],
-                self.cells[offset + 3],
-                self.cells[offset + 4],
-            ];
-            if row.iter().all(|el| marked.contains(el)) {
-                debug!("Found a row: {:?}", row);
-                return true;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    // call is the position in the draw
+    Called {
+        call: usize,
+        number: u8,
+    },
+    Marked {
+        board: usize,
+        cell: usize,
+    },
+    Won {
+        board: usize,
+        call: usize,
+        score: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Win {
+    pub board: usize,
+    pub call: usize,
+    pub number: u8,
+    pub score: u32,
+}
+
+// Boards in the order they won. Boards winning on the same call are in
+// board order, so ties are visible by comparing call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ranking {
+    pub wins: Vec<Win>,
+    pub never_won: Vec<usize>,
+}
+
+// Winning lines for one board size, and which of them each cell is on
+struct Layout {
+    line_len: Vec<usize>,
+    cell_lines: Vec<Vec<usize>>,
+}
+
+impl Layout {
+    fn new(board: &Board, rules: &Rules) -> Layout {
+        let lines = board.lines(rules);
+        let mut cell_lines = vec![vec![]; board.cells.len()];
+        for (l, line) in lines.iter().enumerate() {
+            for &cell in line {
+                cell_lines[cell].push(l);
             }
         }
-        // Columns
-        for c in 0..5 {
-            let col = [
-                self.cells[c],
-                self.cells[c + 5],
-                self.cells[c + 10],
-                self.cells[c + 15],
-                self.cells[c + 20],
-            ];
-            if col.iter().all(|el| marked.contains(el)) {
-                debug!("Found a col: {:?}", col);
-                return true;
-            }
+        Layout {
+            line_len: lines.iter().map(|line| line.len()).collect(),
+            cell_lines,
         }
-        // // Diagonals DON'T COUNT...
-        // let nwse = [self.cells[0], self.cells[6], self.cells[12], self.cells[18], self.cells[24]];
-        // if nwse.iter().all(|el| marked.contains(el)) {
-        //     debug!("Found a nwse: {:?}", nwse);
-        //     return true;
-        // }
-        // let nesw = [self.cells[4], self.cells[8], self.cells[12], self.cells[16], self.cells[20]];
-        // if nesw.iter().all(|el| marked.contains(el)) {
-        //     debug!("Found a nesw: {:?}", nesw);
-        //     return true;
-        // }
-        false
-    }
-
-    fn solve(&mut self, calls: &[u8]) {
-        let mut score: u32 = self.cells.iter().map(|&el| el as u32).sum();
-        let mut marks = Vec::<u8>::new();
-
-        for &round in calls {
-            score = score.saturating_sub(round as u32);
-            // score -= round as u32;
-            marks.push(round);
-            if self.winp(&marks) {
-                self.score = score * (round as u32);
-                break;
-            }
+    }
+}
+
+// Where a board is up to. hits counts the marked cells on each line.
+struct Progress {
+    marked: Vec<bool>,
+    hits: Vec<usize>,
+    unmarked: u32,
+    won: bool,
+}
+
+impl Progress {
+    // Mark a cell, returning whether that completed a line
+    fn mark(&mut self, board: &Board, layout: &Layout, cell: usize) -> bool {
+        if self.marked[cell] {
+            return false;
+        }
+        self.marked[cell] = true;
+        self.unmarked -= board.cells[cell] as u32;
+        let mut complete = false;
+        for &l in &layout.cell_lines[cell] {
+            self.hits[l] += 1;
+            complete |= self.hits[l] == layout.line_len[l];
         }
+        complete
     }
 }
 
-// Default implementation: Chunk on whitespace
-#[aoc_generator(day4)]
-pub fn input_generator(input: &str) -> Vec<Board> {
-    let mut lines = input.lines();
-
-    // Read calls
-    let call = lines
-        .next()
-        .unwrap()
-        .trim()
-        .split(',')
-        .map(|s| s.parse::<u8>().unwrap())
-        .collect::<Vec<_>>();
-
-    debug!("Found calls: {:?}", call);
-
-    let mut boards: Vec<Board> = Vec::new();
-
-    let mut board = Board::new();
-
-    // Read boards
-    for line in lines {
-        if line.trim().is_empty() {
-            if !board.cells.is_empty() {
-                // Go ahead and solve it
-                board.solve(&call);
-                boards.push(board);
+pub struct BingoGame<'a> {
+    bingo: &'a Bingo,
+    layouts: FxHashMap<usize, Layout>,
+    // Every (board, cell) holding each number
+    index: FxHashMap<u8, Vec<(usize, usize)>>,
+    progress: Vec<Progress>,
+    wins: Vec<Win>,
+    events: Vec<Event>,
+}
+
+impl<'a> BingoGame<'a> {
+    pub fn new(bingo: &'a Bingo, rules: Rules) -> BingoGame<'a> {
+        let mut game = BingoGame {
+            bingo,
+            layouts: FxHashMap::default(),
+            index: FxHashMap::default(),
+            progress: vec![],
+            wins: vec![],
+            events: vec![],
+        };
+        for (b, board) in bingo.boards.iter().enumerate() {
+            for (cell, &el) in board.cells.iter().enumerate() {
+                game.index.entry(el).or_default().push((b, cell));
             }
-            board = Board::new();
-        } else {
-            debug!("Parsing line: {}", line);
-            board.cells.append(
-                &mut line
-                    .trim()
-                    .split(' ')
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.parse::<u8>().unwrap())
-                    .collect::<Vec<_>>(),
-            );
+            let layout = game
+                .layouts
+                .entry(board.size)
+                .or_insert_with(|| Layout::new(board, &rules));
+            let mut progress = Progress {
+                marked: vec![false; board.cells.len()],
+                hits: vec![0; layout.line_len.len()],
+                unmarked: board.cells.iter().map(|&el| el as u32).sum(),
+                won: false,
+            };
+            // The free square can only complete a line along with a call
+            if let Some(centre) = board.centre(&rules) {
+                progress.mark(board, layout, centre);
+            }
+            game.progress.push(progress);
         }
+        game
     }
-    // Catch the last board
-    if !board.cells.is_empty() {
-        // Go ahead and solve it
-        board.solve(&call);
-        boards.push(board);
+
+    // Call every number in the draw. Boards stop playing once they've won.
+    pub fn play(&mut self) {
+        for (call, &number) in self.bingo.calls.iter().enumerate() {
+            self.events.push(Event::Called { call, number });
+            let cells = self.index.get(&number).map_or(&[][..], Vec::as_slice);
+            let mut winners = vec![];
+            for &(b, cell) in cells {
+                let progress = &mut self.progress[b];
+                if progress.won || progress.marked[cell] {
+                    continue;
+                }
+                self.events.push(Event::Marked { board: b, cell });
+                let board = &self.bingo.boards[b];
+                if progress.mark(board, &self.layouts[&board.size], cell)
+                    && winners.last() != Some(&b)
+                {
+                    winners.push(b);
+                }
+            }
+            // Numbers are indexed in board order, so winners are too
+            for b in winners {
+                let progress = &mut self.progress[b];
+                progress.won = true;
+                let score = progress.unmarked * number as u32;
+                debug!("Board {} won on call {}: {}", b, call, score);
+                self.events.push(Event::Won {
+                    board: b,
+                    call,
+                    score,
+                });
+                self.wins.push(Win {
+                    board: b,
+                    call,
+                    number,
+                    score,
+                });
+            }
+        }
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
     }
 
-    // We want the first one to be the highest score
-    boards.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-    boards
+    pub fn ranking(&self) -> Ranking {
+        Ranking {
+            wins: self.wins.clone(),
+            never_won: (0..self.progress.len())
+                .filter(|&b| !self.progress[b].won)
+                .collect(),
+        }
+    }
+}
+
+pub fn play(bingo: &Bingo, rules: Rules) -> Ranking {
+    let mut game = BingoGame::new(bingo, rules);
+    game.play();
+    game.ranking()
 }
 
 #[aoc(day4, part1)]
-pub fn part1(input: &[Board]) -> u32 {
-    input.first().unwrap().score
+pub fn part1(input: &Bingo) -> u32 {
+    play(input, Rules::default())
+        .wins
+        .first()
+        .map_or(0, |w| w.score)
 }
 
 #[aoc(day4, part2)]
-pub fn part2(input: &[Board]) -> u32 {
-    input.last().unwrap().score
+pub fn part2(input: &Bingo) -> u32 {
+    play(input, Rules::default())
+        .wins
+        .last()
+        .map_or(0, |w| w.score)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rng::Lcg;
 
     const EXAMPLE_INPUT: &str = r#"7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
 
@@ -168,11 +329,129 @@ mod tests {
 
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(&input_generator(EXAMPLE_INPUT)), 4512)
+        assert_eq!(part1(&input_generator(EXAMPLE_INPUT).unwrap()), 4512)
     }
 
     #[test]
     fn part2_ex1() {
-        assert_eq!(part2(&input_generator(EXAMPLE_INPUT)), 1924)
+        assert_eq!(part2(&input_generator(EXAMPLE_INPUT).unwrap()), 1924)
+    }
+
+    fn first_win(input: &str, rules: Rules) -> Option<Win> {
+        play(&input_generator(input).unwrap(), rules)
+            .wins
+            .first()
+            .copied()
+    }
+
+    #[test]
+    fn rule_variants() {
+        // Only the diagonal 1, 5, 9 is called
+        let input = "1,5,9\n\n1 2 3\n4 5 6\n7 8 9\n";
+        assert_eq!(first_win(input, Rules::default()), None);
+        let diagonals = Rules {
+            diagonals: true,
+            ..Rules::default()
+        };
+        let win = first_win(input, diagonals).unwrap();
+        assert_eq!(win.score, (2 + 3 + 4 + 6 + 7 + 8) * 9);
+
+        // With the centre free, the anti-diagonal only needs 3 and 7
+        let free = Rules {
+            diagonals: true,
+            free_centre: true,
+            ..Rules::default()
+        };
+        let win = first_win("3,7\n\n1 2 3\n4 5 6\n7 8 9\n", free).unwrap();
+        assert_eq!(win.score, (1 + 2 + 4 + 6 + 8 + 9) * 7);
+
+        let corners = Rules {
+            four_corners: true,
+            ..Rules::default()
+        };
+        let input = "1,4,13,16\n\n1 2 3 4\n5 6 7 8\n9 10 11 12\n13 14 15 16\n";
+        assert_eq!(input_generator(input).unwrap().boards[0].size(), 4);
+        assert_eq!(first_win(input, corners).unwrap().score, (136 - 34) * 16);
+    }
+
+    #[test]
+    fn game_log() {
+        let bingo = input_generator(EXAMPLE_INPUT).unwrap();
+        let mut game = BingoGame::new(&bingo, Rules::default());
+        game.play();
+        let ranking = game.ranking();
+        let order: Vec<_> = ranking.wins.iter().map(|w| (w.board, w.call)).collect();
+        assert_eq!(order, vec![(2, 11), (0, 13), (1, 14)]);
+        assert!(ranking.never_won.is_empty());
+
+        let events = game.events();
+        assert_eq!(events[0], Event::Called { call: 0, number: 7 });
+        assert_eq!(events[1], Event::Marked { board: 0, cell: 14 });
+        assert!(events.contains(&Event::Won {
+            board: 2,
+            call: 11,
+            score: 4512
+        }));
+
+        // Tied boards both win on the same call, and losers are listed
+        let tie = input_generator("1,2\n\n1 2\n3 4\n\n2 1\n4 3\n\n5 6\n7 8\n").unwrap();
+        let ranking = play(&tie, Rules::default());
+        assert_eq!(ranking.wins.len(), 2);
+        assert_eq!(ranking.wins[0].call, ranking.wins[1].call);
+        assert_eq!(ranking.never_won, vec![2]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = input_generator("1,2\n\n1 2\n3\n").unwrap_err();
+        assert_eq!(err.line, 4);
+        assert_eq!(err.expected, "a row of 2 numbers");
+        assert!(input_generator("1,2\n\n1 2\n3 4\n5 6\n").is_err());
+    }
+
+    #[test]
+    fn many_boards() {
+        // Pseudo-random boards of 0..100 without repeats, against a plain
+        // check of every row and column after every call
+        let mut rng = Lcg::new(12345);
+        let mut shuffled = |n: usize| {
+            let mut v: Vec<u8> = (0..100).collect();
+            for i in (1..100).rev() {
+                v.swap(i, rng.below(i as u64 + 1) as usize);
+            }
+            v.truncate(n);
+            v
+        };
+        let calls = shuffled(100);
+        let boards: Vec<Board> = (0..2000)
+            .map(|_| Board {
+                cells: shuffled(25),
+                size: 5,
+            })
+            .collect();
+        let bingo = Bingo { calls, boards };
+        let ranking = play(&bingo, Rules::default());
+
+        for win in ranking.wins.iter().step_by(97) {
+            let board = &bingo.boards[win.board];
+            let called = &bingo.calls[..=win.call];
+            let complete = |called: &[u8]| {
+                let done = |i: usize| called.contains(&board.cells[i]);
+                (0..5).any(|r| (0..5).all(|c| done(r * 5 + c)))
+                    || (0..5).any(|c| (0..5).all(|r| done(r * 5 + c)))
+            };
+            // Won on this call and not before
+            assert!(complete(called));
+            assert!(!complete(&called[..win.call]));
+            let unmarked: u32 = board
+                .cells
+                .iter()
+                .filter(|el| !called.contains(el))
+                .map(|&el| el as u32)
+                .sum();
+            assert_eq!(win.score, unmarked * win.number as u32);
+        }
+        assert_eq!(ranking.wins.len() + ranking.never_won.len(), 2000);
+        assert!(ranking.wins.windows(2).all(|w| w[0].call <= w[1].call));
     }
 }
@@ -1,109 +1,421 @@
-use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-#[derive(Debug, Default, Clone)]
-pub struct Line {
-    x0: i32,
-    y0: i32,
-    x1: i32,
-    y1: i32,
+use crate::geom::{gcd, Box2, Orientation, Point2, Segment};
+use crate::parse::{self, Input};
+
+fn coord(inp: &mut Input) -> parse::Result<Point2> {
+    let x = parse::int(inp)?;
+    inp.tag(",")?;
+    let y = parse::int(inp)?;
+    Ok(Point2::new(x, y))
 }
 
-// Default implementation: Chunk on whitespace
+// 2 coords separated by ->
 #[aoc_generator(day5)]
-pub fn input_generator(input: &str) -> Vec<Line> {
-    input
-        .lines()
-        .map(|l| {
-            let mut toks = l.trim().split(' ');
-            // 2 coords separated by ->
-            let coord1 = toks
-                .next()
-                .unwrap()
-                .split(',')
-                .map(|s| s.parse::<i32>().unwrap())
-                .collect::<Vec<_>>();
-            // skip the ->
-            toks.next();
-            let coord2 = toks
-                .next()
-                .unwrap()
-                .split(',')
-                .map(|s| s.parse::<i32>().unwrap())
-                .collect::<Vec<_>>();
-
-            Line {
-                x0: coord1[0],
-                y0: coord1[1],
-                x1: coord2[0],
-                y1: coord2[1],
+pub fn input_generator(input: &str) -> parse::Result<Vec<Segment>> {
+    parse::lines(input, |inp| {
+        let a = coord(inp)?;
+        inp.skip_ws();
+        inp.tag("->")?;
+        inp.skip_ws();
+        let b = coord(inp)?;
+        Ok(Segment::new(a, b))
+    })
+}
+
+// How a line turns into cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Raster {
+    // Only points exactly on the line
+    Lattice,
+    // Every cell the line passes through
+    Bresenham,
+}
+
+// How many lines cover each point, counting only lines with the given orientations
+pub fn draw(input: &[Segment], keep: &[Orientation], raster: Raster) -> HashMap<Point2, i32> {
+    let mut dwg = HashMap::new();
+    for line in input.iter().filter(|l| keep.contains(&l.orientation())) {
+        let points: Box<dyn Iterator<Item = Point2>> = match raster {
+            Raster::Lattice => Box::new(line.points()),
+            Raster::Bresenham => Box::new(line.covered()),
+        };
+        for p in points {
+            *dwg.entry(p).or_insert(0) += 1;
+        }
+    }
+    dwg
+}
+
+pub fn overlaps(input: &[Segment], keep: &[Orientation], raster: Raster) -> usize {
+    let dwg = draw(input, keep, raster);
+    dwg.values().filter(|&&v| v >= 2).count()
+}
+
+#[aoc(day5, part1)]
+pub fn part1(input: &[Segment]) -> usize {
+    use Orientation::*;
+    overlaps(input, &[Horizontal, Vertical], Raster::Lattice)
+}
+
+#[aoc(day5, part2)]
+pub fn part2(input: &[Segment]) -> usize {
+    use Orientation::*;
+    overlaps(input, &[Horizontal, Vertical, Diagonal], Raster::Lattice)
+}
+
+// Inclusive runs of positions along a line
+type Runs = Vec<(i64, i64)>;
+
+// Parallel lines with the same primitive direction (dx, dy), each keyed by
+// dy*x - dx*y. Along a line, a point's position is dx*x + dy*y, so
+// neighbouring lattice points are dx^2 + dy^2 apart.
+struct Family {
+    dir: (i64, i64),
+    // Where each line is covered at least once, and at least twice
+    once: BTreeMap<i64, Runs>,
+    twice: BTreeMap<i64, Runs>,
+}
+
+impl Family {
+    fn key(&self, p: Point2) -> i64 {
+        self.dir.1 * p.x as i64 - self.dir.0 * p.y as i64
+    }
+
+    fn pos(&self, p: Point2) -> i64 {
+        self.dir.0 * p.x as i64 + self.dir.1 * p.y as i64
+    }
+
+    fn spacing(&self) -> i64 {
+        self.dir.0 * self.dir.0 + self.dir.1 * self.dir.1
+    }
+
+    fn point(&self, key: i64, pos: i64) -> Point2 {
+        let (dx, dy) = self.dir;
+        let s = self.spacing();
+        Point2::new(
+            ((dy * key + dx * pos) / s) as i32,
+            ((dy * pos - dx * key) / s) as i32,
+        )
+    }
+
+    fn cells(&self, runs: &BTreeMap<i64, Runs>) -> usize {
+        runs.values()
+            .flatten()
+            .map(|&(lo, hi)| ((hi - lo) / self.spacing() + 1) as usize)
+            .sum()
+    }
+}
+
+fn covers(runs: &BTreeMap<i64, Runs>, key: i64, pos: i64) -> bool {
+    runs.get(&key).is_some_and(|runs| {
+        let i = runs.partition_point(|&(lo, _)| lo <= pos);
+        i > 0 && runs[i - 1].1 >= pos
+    })
+}
+
+// Lowest terms, pointing right (or down when vertical). A single point
+// counts as horizontal.
+fn direction(line: &Segment) -> (i64, i64) {
+    let d = line.b - line.a;
+    let n = gcd(d.x.unsigned_abs(), d.y.unsigned_abs()) as i32;
+    let (dx, dy) = if n == 0 { (1, 0) } else { (d.x / n, d.y / n) };
+    if dx < 0 || (dx == 0 && dy < 0) {
+        (-dx as i64, -dy as i64)
+    } else {
+        (dx as i64, dy as i64)
+    }
+}
+
+// Sweep along a line, turning runs into where they cover once and twice
+fn merge(runs: &[(i64, i64)], spacing: i64) -> (Runs, Runs) {
+    let mut events: Vec<(i64, i32)> = runs
+        .iter()
+        .flat_map(|&(lo, hi)| [(lo, 1), (hi + spacing, -1)])
+        .collect();
+    events.sort_unstable();
+    let (mut once, mut twice) = (vec![], vec![]);
+    let mut depth = 0;
+    let (mut once_from, mut twice_from) = (0, 0);
+    for (pos, delta) in events {
+        let before = depth;
+        depth += delta;
+        match (before, depth) {
+            (0, 1) => once_from = pos,
+            (1, 0) => once.push((once_from, pos - spacing)),
+            (1, 2) => twice_from = pos,
+            (2, 1) => twice.push((twice_from, pos - spacing)),
+            _ => {}
+        }
+    }
+    (once, twice)
+}
+
+fn families(input: &[Segment], keep: &[Orientation]) -> Vec<Family> {
+    let mut runs: HashMap<(i64, i64), BTreeMap<i64, Runs>> = HashMap::new();
+    for line in input.iter().filter(|l| keep.contains(&l.orientation())) {
+        let dir = direction(line);
+        let f = Family {
+            dir,
+            once: BTreeMap::new(),
+            twice: BTreeMap::new(),
+        };
+        let (a, b) = (f.pos(line.a), f.pos(line.b));
+        runs.entry(dir)
+            .or_default()
+            .entry(f.key(line.a))
+            .or_default()
+            .push((a.min(b), a.max(b)));
+    }
+    runs.into_iter()
+        .map(|(dir, lines)| {
+            let mut f = Family {
+                dir,
+                once: BTreeMap::new(),
+                twice: BTreeMap::new(),
+            };
+            for (key, runs) in lines {
+                let (once, twice) = merge(&runs, f.spacing());
+                f.once.insert(key, once);
+                if !twice.is_empty() {
+                    f.twice.insert(key, twice);
+                }
             }
+            f
         })
         .collect()
 }
 
-fn step_from(x0: i32, x1: i32) -> i32 {
-    match x0.cmp(&x1) {
-        Ordering::Less => 1,
-        Ordering::Equal => 0,
-        Ordering::Greater => -1,
+// Where line k1 of f meets line k2 of g, if that's a lattice point
+fn crossing(f: &Family, k1: i64, g: &Family, k2: i64) -> Option<Point2> {
+    let (a1, b1) = (f.dir.1, -f.dir.0);
+    let (a2, b2) = (g.dir.1, -g.dir.0);
+    let det = a1 * b2 - a2 * b1;
+    let (x, y) = (k1 * b2 - k2 * b1, a1 * k2 - a2 * k1);
+    if det == 0 || x % det != 0 || y % det != 0 {
+        return None;
     }
+    Some(Point2::new((x / det) as i32, (y / det) as i32))
 }
 
-fn draw(input: &[&Line]) -> HashMap<(i32, i32), i32> {
-    let mut dwg = HashMap::new();
-    for &line in input {
-        let xstep = step_from(line.x0, line.x1);
-        let ystep = step_from(line.y0, line.y1);
+// Same count as overlaps with lattice points, without a cell per point.
+// Overlaps between parallel lines come from merging runs; the rest are
+// crossings of lines with different directions, found by range queries.
+pub fn overlaps_sweep(input: &[Segment], keep: &[Orientation]) -> usize {
+    let families = families(input, keep);
+    let mut crossings = HashSet::new();
+    for (i, f) in families.iter().enumerate() {
+        for g in &families[i + 1..] {
+            for (&k1, runs) in &f.once {
+                for &(lo, hi) in runs {
+                    let (ka, kb) = (g.key(f.point(k1, lo)), g.key(f.point(k1, hi)));
+                    for (&k2, _) in g.once.range(ka.min(kb)..=ka.max(kb)) {
+                        if let Some(p) = crossing(f, k1, g, k2) {
+                            if (lo..=hi).contains(&f.pos(p)) && covers(&g.once, k2, g.pos(p)) {
+                                crossings.insert(p);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
 
-        let mut x = line.x0;
-        let mut y = line.y0;
+    // A crossing already overlapping within k families was counted k times
+    let mut total: usize = families.iter().map(|f| f.cells(&f.twice)).sum();
+    for p in crossings {
+        match families
+            .iter()
+            .filter(|f| covers(&f.twice, f.key(p), f.pos(p)))
+            .count()
+        {
+            0 => total += 1,
+            k => total -= k - 1,
+        }
+    }
+    total
+}
 
-        // println!(
-        //     "Drawing {:?} starting at ({}, {}) by steps {}, {}",
-        //     line, x, y, xstep, ystep
-        // );
+#[aoc(day5, part1, sweep)]
+pub fn part1_sweep(input: &[Segment]) -> usize {
+    use Orientation::*;
+    overlaps_sweep(input, &[Horizontal, Vertical])
+}
 
-        loop {
-            // Draw at point
-            *dwg.entry((x, y)).or_insert(0) += 1;
+#[aoc(day5, part2, sweep)]
+pub fn part2_sweep(input: &[Segment]) -> usize {
+    use Orientation::*;
+    overlaps_sweep(input, &[Horizontal, Vertical, Diagonal])
+}
 
-            // If we drew the last point, break
-            if x == line.x1 && y == line.y1 {
-                break;
-            }
+// Which part of the overlap map to export
+#[derive(Debug, Clone, Copy)]
+pub struct HeatmapOptions {
+    // Defaults to the box around everything shown
+    pub crop: Option<Box2>,
+    // Cells covered fewer times than this are left blank
+    pub min_count: i32,
+    // Refuse to allocate a bigger raster than this
+    pub max_cells: u64,
+}
 
-            // And step
-            x += xstep;
-            y += ystep;
+impl Default for HeatmapOptions {
+    fn default() -> Self {
+        HeatmapOptions {
+            crop: None,
+            min_count: 1,
+            max_cells: 1 << 24,
         }
     }
-    dwg
 }
 
-#[aoc(day5, part1)]
-pub fn part1(input: &[Line]) -> usize {
-    let lines = input
-        .iter()
-        .filter(|&l| l.x0 == l.x1 || l.y0 == l.y1)
-        .collect::<Vec<_>>();
-    let dwg = draw(&lines);
-    dwg.values().filter(|&&v| v >= 2).count()
+// Line counts for every cell in a box, row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    pub bounds: Box2,
+    counts: Vec<i32>,
+    max: i32,
 }
 
-#[aoc(day5, part2)]
-pub fn part2(input: &[Line]) -> usize {
-    let lines = input
-        .iter()
-        .filter(|&l| l.x0 == l.x1 || l.y0 == l.y1 || ((l.y1 - l.y0).abs() == (l.x1 - l.x0).abs()))
-        .collect::<Vec<_>>();
-    let dwg = draw(&lines);
-    dwg.values().filter(|&&v| v >= 2).count()
+impl Heatmap {
+    // None when nothing is left to show, or when the box would need more than
+    // max_cells cells. The sparse drawing is all there is then.
+    pub fn new(dwg: &HashMap<Point2, i32>, options: &HeatmapOptions) -> Option<Heatmap> {
+        let shown = dwg.iter().filter(|&(p, &n)| {
+            n >= options.min_count && options.crop.iter().all(|c| c.contains(*p))
+        });
+        let bounds = options
+            .crop
+            .or_else(|| Box2::enclosing(shown.clone().map(|(&p, _)| p)))?;
+        if bounds.area() > options.max_cells {
+            return None;
+        }
+        let mut map = Heatmap {
+            bounds,
+            counts: vec![0; bounds.area() as usize],
+            max: 0,
+        };
+        for (&p, &n) in shown {
+            let i = map.index(p);
+            map.counts[i] = n;
+            map.max = map.max.max(n);
+        }
+        Some(map)
+    }
+
+    pub fn width(&self) -> usize {
+        (self.bounds.max.x - self.bounds.min.x + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.bounds.max.y - self.bounds.min.y + 1) as usize
+    }
+
+    fn index(&self, p: Point2) -> usize {
+        (p.y - self.bounds.min.y) as usize * self.width() + (p.x - self.bounds.min.x) as usize
+    }
+
+    pub fn get(&self, p: Point2) -> i32 {
+        if self.bounds.contains(p) {
+            self.counts[self.index(p)]
+        } else {
+            0
+        }
+    }
+
+    // Every shown cell, by row then column
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("x,y,count\n");
+        for (i, &n) in self.counts.iter().enumerate().filter(|&(_, &n)| n > 0) {
+            let x = self.bounds.min.x + (i % self.width()) as i32;
+            let y = self.bounds.min.y + (i / self.width()) as i32;
+            csv += &format!("{},{},{}\n", x, y, n);
+        }
+        csv
+    }
+
+    // Brightness scaled so the most overlapped cell is white
+    fn pixels(&self) -> Vec<u8> {
+        self.counts
+            .iter()
+            .map(|&n| (n as i64 * 255 / self.max.max(1) as i64) as u8)
+            .collect()
+    }
+
+    // Binary greyscale PGM
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let mut out = format!("P5\n{} {}\n255\n", self.width(), self.height()).into_bytes();
+        out.extend(self.pixels());
+        out
+    }
+
+    // Greyscale PNG, stored without compression
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut raw = vec![];
+        for row in self.pixels().chunks(self.width()) {
+            // No filter
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+
+        let mut zlib = vec![0x78, 0x01];
+        let blocks: Vec<&[u8]> = raw.chunks(0xffff).collect();
+        for (i, block) in blocks.iter().enumerate() {
+            zlib.push((i + 1 == blocks.len()) as u8);
+            let len = block.len() as u16;
+            zlib.extend_from_slice(&len.to_le_bytes());
+            zlib.extend_from_slice(&(!len).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+        let mut header = vec![];
+        header.extend_from_slice(&(self.width() as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height() as u32).to_be_bytes());
+        // 8 bit greyscale, default compression, filtering and no interlace
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+        let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        for (kind, data) in [(b"IHDR", header), (b"IDAT", zlib), (b"IEND", vec![])] {
+            png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            let start = png.len();
+            png.extend_from_slice(kind);
+            png.extend_from_slice(&data);
+            let crc = crc32(&png[start..]);
+            png.extend_from_slice(&crc.to_be_bytes());
+        }
+        png
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rng::Lcg;
 
     const EXAMPLE_INPUT: &str = r#"0,9 -> 5,9
 8,0 -> 0,8
@@ -119,11 +431,115 @@ mod tests {
 
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(&input_generator(EXAMPLE_INPUT)), 5)
+        assert_eq!(part1(&input_generator(EXAMPLE_INPUT).unwrap()), 5);
+        assert_eq!(part1_sweep(&input_generator(EXAMPLE_INPUT).unwrap()), 5);
     }
 
     #[test]
     fn part2_ex1() {
-        assert_eq!(part2(&input_generator(EXAMPLE_INPUT)), 12)
+        assert_eq!(part2(&input_generator(EXAMPLE_INPUT).unwrap()), 12);
+        assert_eq!(part2_sweep(&input_generator(EXAMPLE_INPUT).unwrap()), 12);
+    }
+
+    #[test]
+    fn any_slope() {
+        use Orientation::*;
+        let lines = input_generator("0,0 -> 6,4\n3,0 -> 3,4\n0,4 -> 6,0\n").unwrap();
+        // Only (3,2) is on all three lines exactly
+        assert_eq!(overlaps(&lines, &[Vertical, Oblique], Raster::Lattice), 1);
+        let dwg = draw(&lines, &[Vertical, Oblique], Raster::Lattice);
+        assert_eq!(dwg[&Point2::new(3, 2)], 3);
+        assert_eq!(dwg.len(), 3 + 5 + 3 - 2);
+        // Drawn as cells, the slopes cross the vertical elsewhere too
+        let cells = draw(&lines, &[Vertical, Oblique], Raster::Bresenham);
+        assert_eq!(cells.values().sum::<i32>(), 7 + 5 + 7);
+        assert_eq!(overlaps(&lines, &[Vertical], Raster::Bresenham), 0);
+    }
+
+    #[test]
+    fn sweep_matches_raster() {
+        use Orientation::*;
+        let mut rng = Lcg::new(7);
+        let mut next = |n: i32| rng.below(n as u64) as i32;
+        let mut lines = vec![];
+        for _ in 0..600 {
+            let a = Point2::new(next(300), next(300));
+            let len = next(80);
+            let d = match next(5) {
+                0 => Point2::new(len, 0),
+                1 => Point2::new(0, -len),
+                2 => Point2::new(len, len),
+                3 => Point2::new(-len, len),
+                // Slopes of 1/2, 2/3 and the like
+                _ => Point2::new(2 + next(3), 1 + next(2)) * next(20),
+            };
+            lines.push(Segment::new(a, a + d));
+        }
+        let all = [Horizontal, Vertical, Diagonal, Oblique];
+        for keep in [&all[..2], &all[..3], &all[..]] {
+            assert_eq!(
+                overlaps_sweep(&lines, keep),
+                overlaps(&lines, keep, Raster::Lattice)
+            );
+        }
+
+        // Far too big to rasterize: two long lines overlapping by a million
+        // cells, crossed once by a third
+        let big = input_generator(
+            "0,0 -> 0,900000000\n0,899000000 -> 0,999999999\n-5,5 -> 999999994,1000000004\n",
+        )
+        .unwrap();
+        assert_eq!(overlaps_sweep(&big, &all), 1_000_002);
+    }
+
+    #[test]
+    fn heatmap() {
+        use Orientation::*;
+        let dwg = draw(
+            &input_generator(EXAMPLE_INPUT).unwrap(),
+            &[Horizontal, Vertical, Diagonal],
+            Raster::Lattice,
+        );
+        let map = Heatmap::new(&dwg, &HeatmapOptions::default()).unwrap();
+        assert_eq!((map.width(), map.height()), (10, 10));
+        assert_eq!(map.get(Point2::new(2, 0)), 1);
+        assert_eq!(map.get(Point2::new(4, 4)), 3);
+
+        let overlaps = HeatmapOptions {
+            min_count: 2,
+            ..HeatmapOptions::default()
+        };
+        let map = Heatmap::new(&dwg, &overlaps).unwrap();
+        assert_eq!(map.to_csv().lines().count(), 1 + 12);
+        assert!(map.to_csv().contains("\n4,4,3\n"));
+        assert_eq!(map.get(Point2::new(2, 0)), 0);
+
+        let cropped = HeatmapOptions {
+            crop: Some(Box2::new(Point2::new(0, 0), Point2::new(3, 1))),
+            ..HeatmapOptions::default()
+        };
+        let map = Heatmap::new(&dwg, &cropped).unwrap();
+        let pgm = map.to_pgm();
+        assert!(pgm.starts_with(b"P5\n4 2\n255\n"));
+        assert_eq!(pgm.len(), 11 + 8);
+        assert_eq!(
+            map.to_csv(),
+            "x,y,count\n0,0,1\n2,0,1\n1,1,1\n2,1,1\n3,1,1\n"
+        );
+
+        let png = map.to_png();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert!(png.ends_with(&[0xae, 0x42, 0x60, 0x82]));
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+
+        // Two far-apart points would need a huge raster
+        let sparse = HashMap::from([(Point2::new(0, 0), 1), (Point2::new(1 << 20, 1 << 20), 1)]);
+        assert_eq!(Heatmap::new(&sparse, &HeatmapOptions::default()), None);
+        let small = HeatmapOptions {
+            max_cells: 99,
+            ..HeatmapOptions::default()
+        };
+        assert_eq!(Heatmap::new(&dwg, &small), None);
     }
 }
@@ -1,5 +1,7 @@
 use std::collections::VecDeque;
 
+use num_bigint::BigUint;
+
 #[aoc_generator(day6)]
 pub fn input_generator(input: &str) -> Vec<u64> {
     input
@@ -9,122 +11,424 @@ pub fn input_generator(input: &str) -> Vec<u64> {
         .collect()
 }
 
-pub fn input_helper(input: &[u64]) -> [u64; 9] {
-    // INITIALIZE
-    let mut fish: [u64; 9] = [0; 9];
+// How a fish's timer runs. Timers count down to 0, and a fish at 0 spawns
+// and restarts at cycle - 1, while its young start at cycle + newborn_delay - 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lifecycle {
+    pub cycle: usize,
+    pub newborn_delay: usize,
+    // Young per spawning
+    pub litter: u64,
+    // Fish die once they've spawned this many times. Must be at least 1.
+    pub spawns: Option<usize>,
+}
+
+impl Lifecycle {
+    pub const LANTERNFISH: Lifecycle = Lifecycle {
+        cycle: 7,
+        newborn_delay: 2,
+        litter: 1,
+        spawns: None,
+    };
+
+    // Distinct timer values
+    pub fn timers(&self) -> usize {
+        self.cycle + self.newborn_delay
+    }
 
-    for &i in input {
-        fish[i as usize] += 1;
+    // Fish are also told apart by how often they've spawned, if that matters
+    pub fn generations(&self) -> usize {
+        self.spawns.unwrap_or(1)
     }
-    fish
 }
 
-fn sim_3(init: [u64; 9], days: usize) -> u64 {
-    let mut fish = init;
+// How many fish there are with each timer. counts[g * timers + t] is the
+// number that have spawned g times and have timer t.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Population {
+    pub life: Lifecycle,
+    pub counts: Vec<u64>,
+}
 
-    for _ in 0..days {
-        fish.rotate_left(1);
-        fish[6] += fish[8];
+impl Population {
+    // Every fish starts never having spawned
+    pub fn new(life: Lifecycle, timers: &[u64]) -> Population {
+        assert!(life.cycle > 0, "cycle must be at least 1 day");
+        assert!(
+            life.spawns != Some(0),
+            "fish must live to spawn at least once"
+        );
+        let mut counts = vec![0; life.timers() * life.generations()];
+        for &t in timers {
+            counts[t as usize] += 1;
+        }
+        Population { life, counts }
     }
 
-    fish.iter().sum()
+    pub fn step(&mut self) {
+        let n = self.life.timers();
+        let mut next = vec![0; self.counts.len()];
+        let mut born = 0;
+        for (i, &count) in self.counts.iter().enumerate() {
+            let (g, t) = (i / n, i % n);
+            if t > 0 {
+                next[i - 1] += count;
+                continue;
+            }
+            born += count * self.life.litter;
+            match self.life.spawns {
+                None => next[self.life.cycle - 1] += count,
+                Some(spawns) if g + 1 < spawns => next[(g + 1) * n + self.life.cycle - 1] += count,
+                // Spawned its last
+                Some(_) => {}
+            }
+        }
+        next[n - 1] += born;
+        self.counts = next;
+    }
+
+    pub fn run(&mut self, days: usize) {
+        for _ in 0..days {
+            self.step();
+        }
+    }
+
+    // Fish with each timer, whatever their generation
+    pub fn by_timer(&self) -> Vec<u64> {
+        let n = self.life.timers();
+        let mut timers = vec![0; n];
+        for (i, &count) in self.counts.iter().enumerate() {
+            timers[i % n] += count;
+        }
+        timers
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
 }
 
-fn sim_2(init: [u64; 9], days: usize) -> u64 {
-    let mut fish = init;
+// The original simulators, as other ways to step a population through a day.
+// They only keep one generation, so fish must never die.
+impl Population {
+    // Fish at 0 restart their cycle, and their young go in the last slot
+    fn spawned(&mut self, spawning: u64) {
+        assert!(self.life.spawns.is_none(), "only immortal fish");
+        let n = self.counts.len();
+        self.counts[n - 1] = spawning * self.life.litter;
+        self.counts[self.life.cycle - 1] += spawning;
+    }
 
-    for _ in 0..days {
-        let v = fish[0];
-        for i in 1..=8 {
-            fish[i - 1] = fish[i];
+    // Build the next day's counts from scratch
+    pub fn step_unrolled(&mut self) {
+        let mut next = vec![0; self.counts.len()];
+        next[..self.counts.len() - 1].copy_from_slice(&self.counts[1..]);
+        let spawning = std::mem::replace(&mut self.counts, next)[0];
+        self.spawned(spawning);
+    }
+
+    // Shift every timer down a slot, one at a time
+    pub fn step_loop(&mut self) {
+        let spawning = self.counts[0];
+        for i in 1..self.counts.len() {
+            self.counts[i - 1] = self.counts[i];
         }
-        fish[6] += v;
-        fish[8] = v;
+        self.spawned(spawning);
     }
 
-    fish.iter().sum()
+    pub fn step_rotate(&mut self) {
+        let spawning = self.counts[0];
+        self.counts.rotate_left(1);
+        self.spawned(spawning);
+    }
+
+    // Spawners come off the front of a queue and their young go on the back
+    pub fn run_vecdeque(&mut self, days: usize) {
+        assert!(self.life.spawns.is_none(), "only immortal fish");
+        let mut fish = VecDeque::from(std::mem::take(&mut self.counts));
+        for _ in 0..days {
+            let spawning = fish.pop_front().unwrap();
+            fish.push_back(spawning * self.life.litter);
+            fish[self.life.cycle - 1] += spawning;
+        }
+        self.counts = fish.into();
+    }
 }
 
-fn sim_4(init: [u64; 9], days: usize) -> u64 {
-    let mut fish = VecDeque::from_iter(init);
+// What counts are kept in when fast-forwarding
+pub trait Ring {
+    type T: Clone;
+    // The ring's version of n
+    fn lift(&self, n: u64) -> Self::T;
+    fn add(&self, a: &Self::T, b: &Self::T) -> Self::T;
+    fn mul(&self, a: &Self::T, b: &Self::T) -> Self::T;
+}
 
-    for _ in 0..days {
-        let spawning = fish.pop_front().unwrap();
-        fish[6] += spawning;
-        fish.push_back(spawning);
+// Arbitrary precision
+pub struct Exact;
+
+impl Ring for Exact {
+    type T = BigUint;
+
+    fn lift(&self, n: u64) -> BigUint {
+        BigUint::from(n)
+    }
+
+    fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a + b
     }
 
-    fish.iter().sum()
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a * b
+    }
 }
 
-fn sim(init: [u64; 9], days: usize) -> u64 {
-    let mut fish = init;
+// Remainders modulo m
+pub struct Modulo(u64);
 
-    for _ in 0..days {
-        let mut next_fish: [u64; 9] = [0; 9];
+impl Modulo {
+    pub fn new(m: u64) -> Modulo {
+        assert!(m > 0, "modulus must be at least 1");
+        Modulo(m)
+    }
+}
+
+impl Ring for Modulo {
+    type T = u64;
+
+    fn lift(&self, n: u64) -> u64 {
+        n % self.0
+    }
+
+    fn add(&self, a: &u64, b: &u64) -> u64 {
+        ((*a as u128 + *b as u128) % self.0 as u128) as u64
+    }
+
+    fn mul(&self, a: &u64, b: &u64) -> u64 {
+        (*a as u128 * *b as u128 % self.0 as u128) as u64
+    }
+}
+
+type Matrix<T> = Vec<Vec<T>>;
+
+fn mat_mul<R: Ring>(ring: &R, a: &Matrix<R::T>, b: &Matrix<R::T>) -> Matrix<R::T> {
+    let n = a.len();
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| {
+                    (0..n).fold(ring.lift(0), |acc, k| {
+                        ring.add(&acc, &ring.mul(&a[i][k], &b[k][j]))
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+impl Population {
+    // A day as a matrix: column j is where the fish in slot j end up
+    fn transition(&self) -> Matrix<u64> {
+        let n = self.counts.len();
+        let mut m = vec![vec![0; n]; n];
+        for j in 0..n {
+            let mut unit = Population {
+                life: self.life,
+                counts: vec![0; n],
+            };
+            unit.counts[j] = 1;
+            unit.step();
+            for (row, c) in m.iter_mut().zip(unit.counts) {
+                row[j] = c;
+            }
+        }
+        m
+    }
+
+    // Total after days, by squaring the day's matrix, so in O(log days)
+    // matrix products
+    pub fn fast_forward<R: Ring>(&self, ring: &R, days: u64) -> R::T {
+        let n = self.counts.len();
+        let lift = |m: Matrix<u64>| -> Matrix<R::T> {
+            m.iter()
+                .map(|row| row.iter().map(|&v| ring.lift(v)).collect())
+                .collect()
+        };
+        let mut power = lift(self.transition());
+        let mut acc = lift(
+            (0..n)
+                .map(|i| (0..n).map(|j| (i == j) as u64).collect())
+                .collect(),
+        );
+        let mut days = days;
+        while days > 0 {
+            if days & 1 == 1 {
+                acc = mat_mul(ring, &acc, &power);
+            }
+            power = mat_mul(ring, &power, &power);
+            days >>= 1;
+        }
+        let mut total = ring.lift(0);
+        for row in &acc {
+            for (m, &c) in row.iter().zip(&self.counts) {
+                total = ring.add(&total, &ring.mul(m, &ring.lift(c)));
+            }
+        }
+        total
+    }
+}
+
+// Fish with each timer at the start of every day, day 0 first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeline {
+    pub days: Vec<Vec<u64>>,
+}
+
+impl Population {
+    // Snapshots of the timers, starting with today's. Never ends.
+    pub fn snapshots(&self) -> impl Iterator<Item = Vec<u64>> {
+        let mut fish = self.clone();
+        std::iter::repeat_with(move || {
+            let timers = fish.by_timer();
+            fish.step();
+            timers
+        })
+    }
 
-        next_fish[8] = fish[0];
-        next_fish[7] = fish[8];
-        next_fish[6] = fish[7] + fish[0];
-        next_fish[5] = fish[6];
-        next_fish[4] = fish[5];
-        next_fish[3] = fish[4];
-        next_fish[2] = fish[3];
-        next_fish[1] = fish[2];
-        next_fish[0] = fish[1];
+    pub fn timeline(&self, days: usize) -> Timeline {
+        Timeline {
+            days: self.snapshots().take(days + 1).collect(),
+        }
+    }
+}
 
-        fish = next_fish;
+impl Timeline {
+    pub fn totals(&self) -> Vec<u64> {
+        self.days.iter().map(|timers| timers.iter().sum()).collect()
     }
 
-    fish.iter().sum()
+    pub fn to_csv(&self) -> String {
+        let timers = self.days.first().map_or(0, |t| t.len());
+        let mut csv = String::from("day");
+        for t in 0..timers {
+            csv += &format!(",t{}", t);
+        }
+        csv += ",total\n";
+        for (day, counts) in self.days.iter().enumerate() {
+            csv += &day.to_string();
+            for c in counts {
+                csv += &format!(",{}", c);
+            }
+            csv += &format!(",{}\n", counts.iter().sum::<u64>());
+        }
+        csv
+    }
+
+    // [{"day":0,"timers":[...],"total":n}, ...]
+    pub fn to_json(&self) -> String {
+        let days: Vec<String> = self
+            .days
+            .iter()
+            .enumerate()
+            .map(|(day, counts)| {
+                let timers: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+                format!(
+                    "{{\"day\":{},\"timers\":[{}],\"total\":{}}}",
+                    day,
+                    timers.join(","),
+                    counts.iter().sum::<u64>()
+                )
+            })
+            .collect();
+        format!("[{}]", days.join(","))
+    }
+}
+
+fn sim_model(input: &[u64], days: usize) -> u64 {
+    let mut fish = Population::new(Lifecycle::LANTERNFISH, input);
+    fish.run(days);
+    fish.total()
+}
+
+fn sim_with(input: &[u64], days: usize, step: fn(&mut Population)) -> u64 {
+    let mut fish = Population::new(Lifecycle::LANTERNFISH, input);
+    for _ in 0..days {
+        step(&mut fish);
+    }
+    fish.total()
+}
+
+fn sim(input: &[u64], days: usize) -> u64 {
+    sim_with(input, days, Population::step_unrolled)
+}
+
+fn sim_2(input: &[u64], days: usize) -> u64 {
+    sim_with(input, days, Population::step_loop)
+}
+
+fn sim_3(input: &[u64], days: usize) -> u64 {
+    sim_with(input, days, Population::step_rotate)
+}
+
+fn sim_4(input: &[u64], days: usize) -> u64 {
+    let mut fish = Population::new(Lifecycle::LANTERNFISH, input);
+    fish.run_vecdeque(days);
+    fish.total()
 }
 
 #[aoc(day6, part1, unroll)]
 pub fn part1(input: &[u64]) -> u64 {
-    let fish = input_helper(input);
-    sim(fish, 80)
+    sim(input, 80)
 }
 
 #[aoc(day6, part1, looper)]
 pub fn part1_loop(input: &[u64]) -> u64 {
-    let fish = input_helper(input);
-    sim_2(fish, 80)
+    sim_2(input, 80)
 }
 
 #[aoc(day6, part1, rotate)]
 pub fn part1_rotate(input: &[u64]) -> u64 {
-    let fish = input_helper(input);
-    sim_3(fish, 80)
+    sim_3(input, 80)
 }
 
 #[aoc(day6, part1, vecdeque)]
 pub fn part1_vecdeque(input: &[u64]) -> u64 {
-    let fish = input_helper(input);
-    sim_4(fish, 80)
+    sim_4(input, 80)
 }
 
 #[aoc(day6, part2, unroll)]
 pub fn part2(input: &[u64]) -> u64 {
-    let fish = input_helper(input);
-    sim(fish, 256)
+    sim(input, 256)
 }
 
 #[aoc(day6, part2, looper)]
 pub fn part2_loop(input: &[u64]) -> u64 {
-    let fish = input_helper(input);
-    sim_2(fish, 256)
+    sim_2(input, 256)
 }
 
 #[aoc(day6, part2, rotate)]
 pub fn part2_rotate(input: &[u64]) -> u64 {
-    let fish = input_helper(input);
-    sim_3(fish, 256)
+    sim_3(input, 256)
 }
 
 #[aoc(day6, part2, vecdeque)]
 pub fn part2_vecdeque(input: &[u64]) -> u64 {
-    let fish = input_helper(input);
-    sim_4(fish, 256)
+    sim_4(input, 256)
+}
+
+#[aoc(day6, part1, model)]
+pub fn part1_model(input: &[u64]) -> u64 {
+    sim_model(input, 80)
+}
+
+#[aoc(day6, part2, model)]
+pub fn part2_model(input: &[u64]) -> u64 {
+    sim_model(input, 256)
+}
+
+#[aoc(day6, part2, matrix)]
+pub fn part2_matrix(input: &[u64]) -> BigUint {
+    Population::new(Lifecycle::LANTERNFISH, input).fast_forward(&Exact, 256)
 }
 
 #[cfg(test)]
@@ -143,4 +447,153 @@ mod tests {
     fn part2_ex1() {
         assert_eq!(part2(&input_generator(EXAMPLE_INPUT)), 26984457539)
     }
+
+    #[test]
+    fn model_matches_simulators() {
+        let input = input_generator(EXAMPLE_INPUT);
+        for days in [0, 1, 18, 80, 256] {
+            let expected = sim_model(&input, days);
+            assert_eq!(sim(&input, days), expected);
+            assert_eq!(sim_2(&input, days), expected);
+            assert_eq!(sim_3(&input, days), expected);
+            assert_eq!(sim_4(&input, days), expected);
+        }
+        assert_eq!(sim_model(&input, 18), 26);
+
+        // They step any immortal lifecycle the same way the model does
+        let twins = Lifecycle {
+            cycle: 3,
+            newborn_delay: 0,
+            litter: 2,
+            spawns: None,
+        };
+        let slow = Lifecycle {
+            cycle: 5,
+            newborn_delay: 4,
+            litter: 3,
+            spawns: None,
+        };
+        let steppers: [fn(&mut Population); 3] = [
+            Population::step_unrolled,
+            Population::step_loop,
+            Population::step_rotate,
+        ];
+        for life in [twins, slow] {
+            let mut model = Population::new(life, &[0, 1, 2]);
+            let mut others = vec![model.clone(); steppers.len()];
+            for _ in 0..20 {
+                model.step();
+                for (fish, step) in others.iter_mut().zip(steppers) {
+                    step(fish);
+                    assert_eq!(fish.counts, model.counts);
+                }
+            }
+            let mut queue = Population::new(life, &[0, 1, 2]);
+            queue.run_vecdeque(20);
+            assert_eq!(queue.counts, model.counts);
+        }
+    }
+
+    #[test]
+    fn other_lifecycles() {
+        // Twins every 3 days, newborns ready straight away
+        let mut twins = Population::new(
+            Lifecycle {
+                cycle: 3,
+                newborn_delay: 0,
+                litter: 2,
+                spawns: None,
+            },
+            &[0],
+        );
+        twins.run(9);
+        assert_eq!(twins.total(), 27);
+
+        // Each fish spawns twice then dies, so the population grows like
+        // Fibonacci numbers
+        let mut mortal = Population::new(
+            Lifecycle {
+                cycle: 1,
+                newborn_delay: 0,
+                litter: 1,
+                spawns: Some(2),
+            },
+            &[0],
+        );
+        let totals: Vec<u64> = (0..8)
+            .map(|_| {
+                mortal.step();
+                mortal.total()
+            })
+            .collect();
+        assert_eq!(totals, vec![2, 3, 5, 8, 13, 21, 34, 55]);
+        assert_eq!(mortal.by_timer(), vec![55]);
+    }
+
+    #[test]
+    #[should_panic(expected = "spawn at least once")]
+    fn never_spawning() {
+        let stillborn = Lifecycle {
+            spawns: Some(0),
+            ..Lifecycle::LANTERNFISH
+        };
+        Population::new(stillborn, &[3]);
+    }
+
+    #[test]
+    fn fast_forward() {
+        let fish = Population::new(Lifecycle::LANTERNFISH, &input_generator(EXAMPLE_INPUT));
+        for days in [0, 1, 18, 80, 256] {
+            assert_eq!(
+                fish.fast_forward(&Exact, days),
+                BigUint::from(sim_model(&input_generator(EXAMPLE_INPUT), days as usize))
+            );
+        }
+
+        // Well past u64, exactly and modulo a prime
+        const P: u64 = 1_000_000_007;
+        let exact = fish.fast_forward(&Exact, 100_000);
+        assert_eq!(exact.to_string().len(), 3785);
+        assert_eq!(exact % BigUint::from(P), BigUint::from(663433029_u64));
+        assert_eq!(fish.fast_forward(&Modulo::new(P), 100_000), 663433029);
+        assert_eq!(fish.fast_forward(&Modulo::new(P), 1_000_000), 29352087);
+
+        // Stepping first then fast-forwarding the rest agrees
+        let mut later = fish.clone();
+        later.run(5);
+        let days = 1_000_000_000_000_000_000;
+        assert_eq!(
+            later.fast_forward(&Modulo::new(P), days - 5),
+            fish.fast_forward(&Modulo::new(P), days)
+        );
+        assert_eq!(fish.fast_forward(&Modulo::new(1), days), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "modulus")]
+    fn modulo_zero() {
+        Modulo::new(0);
+    }
+
+    #[test]
+    fn timeline() {
+        let fish = Population::new(Lifecycle::LANTERNFISH, &input_generator(EXAMPLE_INPUT));
+        let timeline = fish.timeline(80);
+        assert_eq!(timeline.days.len(), 81);
+        assert_eq!(timeline.days[1], vec![1, 1, 2, 1, 0, 0, 0, 0, 0]);
+        let totals = timeline.totals();
+        assert_eq!(totals[80], 5934);
+        // Each fish alive 9 days ago has a child, on top of those alive 7 days ago
+        assert!((9..=80).all(|d| totals[d] == totals[d - 7] + totals[d - 9]));
+
+        let csv = timeline.to_csv();
+        assert!(csv.starts_with("day,t0,t1,t2,t3,t4,t5,t6,t7,t8,total\n0,0,1,1,2,1,0,0,0,0,5\n"));
+        assert_eq!(csv.lines().count(), 82);
+        let json = fish.timeline(1).to_json();
+        assert_eq!(
+            json,
+            "[{\"day\":0,\"timers\":[0,1,1,2,1,0,0,0,0],\"total\":5},\
+             {\"day\":1,\"timers\":[1,1,2,1,0,0,0,0,0],\"total\":5}]"
+        );
+    }
 }
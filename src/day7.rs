@@ -1,5 +1,7 @@
 use memoize::memoize;
 use rayon::prelude::*;
+use std::fmt;
+use std::ops::RangeInclusive;
 
 #[aoc_generator(day7)]
 pub fn input_generator(input: &str) -> Vec<i32> {
@@ -10,160 +12,415 @@ pub fn input_generator(input: &str) -> Vec<i32> {
         .collect()
 }
 
-fn const_consumption(input: &[i32], target: i32) -> i32 {
-    input.iter().map(|x| (target - x).abs()).sum()
+// Every position from the leftmost crab to the rightmost
+fn span(input: &[i32]) -> Result<RangeInclusive<i32>, FuelError> {
+    let min_pos = *input.iter().min().ok_or(FuelError::NoCrabs)?;
+    let max_pos = *input.iter().max().ok_or(FuelError::NoCrabs)?;
+    Ok(min_pos..=max_pos)
 }
 
 #[memoize]
-fn mem_const_consumption(input: Vec<i32>, target: i32) -> i32 {
-    input.iter().map(|x| (target - x).abs()).sum()
-}
-
-fn par_const_consumption(input: &[i32], target: i32) -> i32 {
-    input.par_iter().map(|x| (target - x).abs()).sum()
+fn mem_const_consumption(input: Vec<i32>, target: i32) -> Result<i128, FuelError> {
+    total_fuel(&crabs(&input), &Linear, target)
 }
 
 #[aoc(day7, part1, brute)]
-pub fn part1(input: &[i32]) -> i32 {
-    let min_pos: i32 = *input.iter().min().unwrap();
-    let max_pos: i32 = *input.iter().max().unwrap();
+pub fn part1(input: &[i32]) -> Result<i128, FuelError> {
+    let crabs = crabs(input);
 
-    let fuel = (min_pos..=max_pos)
-        .map(|pos| const_consumption(input, pos))
-        .collect::<Vec<i32>>();
-    *fuel.iter().min().unwrap()
+    let fuel = span(input)?
+        .map(|pos| total_fuel(&crabs, &Linear, pos))
+        .collect::<Result<Vec<i128>, _>>()?;
+    Ok(*fuel.iter().min().unwrap())
 }
 
 #[aoc(day7, part1, parallel)]
-pub fn part1_parallel(input: &[i32]) -> i32 {
-    let min_pos: i32 = *input.iter().min().unwrap();
-    let max_pos: i32 = *input.iter().max().unwrap();
+pub fn part1_parallel(input: &[i32]) -> Result<i128, FuelError> {
+    let crabs = crabs(input);
 
-    let fuel = (min_pos..=max_pos)
+    let fuel = span(input)?
         .into_par_iter()
-        .map(|pos| par_const_consumption(input, pos))
-        .collect::<Vec<i32>>();
-    *fuel.iter().min().unwrap()
+        .map(|pos| total_fuel(&crabs, &Linear, pos))
+        .collect::<Result<Vec<i128>, _>>()?;
+    Ok(*fuel.iter().min().unwrap())
 }
 
 #[aoc(day7, part1, memo)]
-pub fn part1_memo(input: &[i32]) -> i32 {
-    let min_pos: i32 = *input.iter().min().unwrap();
-    let max_pos: i32 = *input.iter().max().unwrap();
-
+pub fn part1_memo(input: &[i32]) -> Result<i128, FuelError> {
     let ivec = input.to_vec();
 
-    let fuel = (min_pos..=max_pos)
+    let fuel = span(input)?
         .map(|pos| mem_const_consumption(ivec.clone(), pos))
-        .collect::<Vec<i32>>();
-    *fuel.iter().min().unwrap()
+        .collect::<Result<Vec<i128>, _>>()?;
+    Ok(*fuel.iter().min().unwrap())
 }
 
 #[aoc(day7, part1, stats)]
-pub fn part1_stats(input: &[i32]) -> i32 {
+pub fn part1_stats(input: &[i32]) -> Result<i128, FuelError> {
+    span(input)?;
     let median: i32 = statistical::median(input) as i32;
-    const_consumption(input, median)
-}
-
-fn cumulative_consumption(input: &[i32], target: i32) -> i32 {
-    input
-        .iter()
-        .map(|x| {
-            let delta = (target - x).abs();
-            delta * (delta + 1) / 2
-        })
-        .sum()
+    total_fuel(&crabs(input), &Linear, median)
 }
 
 #[memoize]
-fn mem_cumulative_consumption(input: Vec<i32>, target: i32) -> i32 {
-    input
-        .iter()
-        .map(|x| {
-            let delta = (target - x).abs();
-            delta * (delta + 1) / 2
-        })
-        .sum()
-}
-
-fn par_cumulative_consumption(input: &[i32], target: i32) -> i32 {
-    input
-        .par_iter()
-        .map(|x| {
-            let delta = (target - x).abs();
-            delta * (delta + 1) / 2
-        })
-        .sum()
+fn mem_cumulative_consumption(input: Vec<i32>, target: i32) -> Result<i128, FuelError> {
+    total_fuel(&crabs(&input), &Triangular, target)
 }
 
 #[aoc(day7, part2, brute)]
-pub fn part2(input: &[i32]) -> i32 {
-    let min_pos: i32 = *input.iter().min().unwrap();
-    let max_pos: i32 = *input.iter().max().unwrap();
+pub fn part2(input: &[i32]) -> Result<i128, FuelError> {
+    let crabs = crabs(input);
 
-    let fuel = (min_pos..=max_pos)
-        .map(|pos| cumulative_consumption(input, pos))
-        .collect::<Vec<i32>>();
+    let fuel = span(input)?
+        .map(|pos| total_fuel(&crabs, &Triangular, pos))
+        .collect::<Result<Vec<i128>, _>>()?;
 
-    *fuel.iter().min().unwrap()
+    Ok(*fuel.iter().min().unwrap())
 }
 
 #[aoc(day7, part2, parallel)]
-pub fn part2_parallel(input: &[i32]) -> i32 {
-    let min_pos: i32 = *input.iter().min().unwrap();
-    let max_pos: i32 = *input.iter().max().unwrap();
+pub fn part2_parallel(input: &[i32]) -> Result<i128, FuelError> {
+    let crabs = crabs(input);
 
-    let fuel = (min_pos..=max_pos)
+    let fuel = span(input)?
         .into_par_iter()
-        .map(|pos| par_cumulative_consumption(input, pos))
-        .collect::<Vec<i32>>();
+        .map(|pos| total_fuel(&crabs, &Triangular, pos))
+        .collect::<Result<Vec<i128>, _>>()?;
 
-    *fuel.iter().min().unwrap()
+    Ok(*fuel.iter().min().unwrap())
 }
 
 #[aoc(day7, part2, memo)]
-pub fn part2_memo(input: &[i32]) -> i32 {
-    let min_pos: i32 = *input.iter().min().unwrap();
-    let max_pos: i32 = *input.iter().max().unwrap();
-
+pub fn part2_memo(input: &[i32]) -> Result<i128, FuelError> {
     let ivec = input.to_vec();
 
-    let fuel = (min_pos..=max_pos)
+    let fuel = span(input)?
         .map(|pos| mem_cumulative_consumption(ivec.clone(), pos))
-        .collect::<Vec<i32>>();
+        .collect::<Result<Vec<i128>, _>>()?;
 
-    *fuel.iter().min().unwrap()
+    Ok(*fuel.iter().min().unwrap())
 }
 
 #[aoc(day7, part2, stats)]
-pub fn part2_stats(input: &[i32]) -> i32 {
-    // Search around the mean
-    let mean: f64 = input.iter().sum::<i32>() as f64 / input.len() as f64;
-    let fuel_floor: i32 = cumulative_consumption(input, mean.floor() as i32);
-    let fuel_ceil: i32 = cumulative_consumption(input, mean.ceil() as i32);
-    std::cmp::min(fuel_floor, fuel_ceil)
+pub fn part2_stats(input: &[i32]) -> Result<i128, FuelError> {
+    span(input)?;
+    let crabs = crabs(input);
+    // The best position is within half a step of the mean
+    let mean: f64 = input.iter().map(|&x| x as f64).sum::<f64>() / input.len() as f64;
+    let lo = (mean - 0.5).floor() as i32;
+    let hi = (mean + 0.5).ceil() as i32;
+    let fuel = (lo..=hi)
+        .map(|pos| total_fuel(&crabs, &Triangular, pos))
+        .collect::<Result<Vec<i128>, _>>()?;
+    Ok(*fuel.iter().min().unwrap())
+}
+
+// A crab submarine, burning weight times the usual fuel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crab {
+    pub pos: i32,
+    pub weight: u32,
+}
+
+impl From<i32> for Crab {
+    fn from(pos: i32) -> Crab {
+        Crab { pos, weight: 1 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuelError {
+    NoCrabs,
+    // The total fuel for this target doesn't fit in an i128
+    Overflow { target: i32 },
+}
+
+impl fmt::Display for FuelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FuelError::NoCrabs => write!(f, "no crabs to align"),
+            FuelError::Overflow { target } => write!(f, "fuel to reach {} overflows", target),
+        }
+    }
+}
+
+impl std::error::Error for FuelError {}
+
+// Fuel to move one crab a distance. Must be convex and never decrease, so
+// the total over all crabs is convex in the target position.
+pub trait CostFn {
+    fn cost(&self, distance: u32) -> i128;
+
+    // Positions the best one is known to lie in, if there's a shortcut
+    fn shortcut(&self, _crabs: &[Crab]) -> Option<RangeInclusive<i32>> {
+        None
+    }
+}
+
+// One unit of fuel per step
+pub struct Linear;
+
+impl CostFn for Linear {
+    fn cost(&self, distance: u32) -> i128 {
+        distance as i128
+    }
+
+    // Anywhere between the lower and upper weighted medians
+    fn shortcut(&self, crabs: &[Crab]) -> Option<RangeInclusive<i32>> {
+        let mut sorted = crabs.to_vec();
+        sorted.sort_unstable_by_key(|c| c.pos);
+        let total: u64 = sorted.iter().map(|c| c.weight as u64).sum();
+        let mut below = 0;
+        let mut lo = None;
+        for c in sorted {
+            below += c.weight as u64;
+            if lo.is_none() && below * 2 >= total {
+                lo = Some(c.pos);
+            }
+            if below * 2 > total {
+                return Some(lo?..=c.pos);
+            }
+        }
+        None
+    }
+}
+
+// Each step costs one more than the last
+pub struct Triangular;
+
+impl CostFn for Triangular {
+    fn cost(&self, distance: u32) -> i128 {
+        let d = distance as i128;
+        d * (d + 1) / 2
+    }
+
+    // The slope is the weighted sum of (target - pos) plus at most half the
+    // total weight either way, so the best is within half a step of the mean
+    fn shortcut(&self, crabs: &[Crab]) -> Option<RangeInclusive<i32>> {
+        let total: i128 = crabs.iter().map(|c| c.weight as i128).sum();
+        if total == 0 {
+            return None;
+        }
+        let sum: i128 = crabs.iter().map(|c| c.weight as i128 * c.pos as i128).sum();
+        // floor(mean - 1/2) and ceil(mean + 1/2), in integers
+        let lo = (2 * sum - total).div_euclid(2 * total);
+        let hi = -(-(2 * sum + total)).div_euclid(2 * total);
+        Some(lo as i32..=hi as i32)
+    }
+}
+
+pub struct Quadratic;
+
+impl CostFn for Quadratic {
+    fn cost(&self, distance: u32) -> i128 {
+        let d = distance as i128;
+        d * d
+    }
+}
+
+impl<F: Fn(u32) -> i128> CostFn for F {
+    fn cost(&self, distance: u32) -> i128 {
+        self(distance)
+    }
+}
+
+pub fn crabs(input: &[i32]) -> Vec<Crab> {
+    input.iter().map(|&pos| Crab::from(pos)).collect()
+}
+
+pub fn total_fuel(crabs: &[Crab], cost: &impl CostFn, target: i32) -> Result<i128, FuelError> {
+    crabs.iter().try_fold(0_i128, |acc, c| {
+        cost.cost(c.pos.abs_diff(target))
+            .checked_mul(c.weight as i128)
+            .and_then(|fuel| acc.checked_add(fuel))
+            .ok_or(FuelError::Overflow { target })
+    })
+}
+
+// Every position using the least fuel, and how much that is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alignment {
+    pub positions: RangeInclusive<i32>,
+    pub fuel: i128,
+}
+
+// The first position in lo..=hi where pred holds, or hi if it never does.
+// pred must be false then true.
+fn first_where(
+    mut lo: i32,
+    mut hi: i32,
+    pred: impl Fn(i32) -> Result<bool, FuelError>,
+) -> Result<i32, FuelError> {
+    while lo < hi {
+        let mid = (lo as i64 + hi as i64).div_euclid(2) as i32;
+        if pred(mid)? {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    Ok(lo)
+}
+
+// The total is convex, so its slope only ever rises. Binary search for where
+// it stops falling and where it starts rising: the minimum lies between.
+// Nothing outside the crabs can be better.
+pub fn align(crabs: &[Crab], cost: &impl CostFn) -> Result<Alignment, FuelError> {
+    let min_pos = crabs
+        .iter()
+        .map(|c| c.pos)
+        .min()
+        .ok_or(FuelError::NoCrabs)?;
+    let max_pos = crabs
+        .iter()
+        .map(|c| c.pos)
+        .max()
+        .ok_or(FuelError::NoCrabs)?;
+    align_within(crabs, cost, min_pos, max_pos)
+}
+
+fn align_within(
+    crabs: &[Crab],
+    cost: &impl CostFn,
+    lo: i32,
+    hi: i32,
+) -> Result<Alignment, FuelError> {
+    let fuel = |pos| total_fuel(crabs, cost, pos);
+
+    let first = first_where(lo, hi, |pos| Ok(fuel(pos + 1)? >= fuel(pos)?))?;
+    let last = first_where(first, hi, |pos| Ok(fuel(pos + 1)? > fuel(pos)?))?;
+    Ok(Alignment {
+        positions: first..=last,
+        fuel: fuel(first)?,
+    })
+}
+
+// Search only where the cost's shortcut says to, if it has one
+pub fn align_fast(crabs: &[Crab], cost: &impl CostFn) -> Result<Alignment, FuelError> {
+    match cost.shortcut(crabs) {
+        Some(range) => align_within(crabs, cost, *range.start(), *range.end()),
+        None => align(crabs, cost),
+    }
+}
+
+#[aoc(day7, part1, convex)]
+pub fn part1_convex(input: &[i32]) -> Result<i128, FuelError> {
+    align(&crabs(input), &Linear).map(|a| a.fuel)
+}
+
+#[aoc(day7, part2, convex)]
+pub fn part2_convex(input: &[i32]) -> Result<i128, FuelError> {
+    align(&crabs(input), &Triangular).map(|a| a.fuel)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rng::Lcg;
 
     const EXAMPLE_INPUT: &str = r#"16,1,2,0,4,2,7,1,2,14
 "#;
 
     #[test]
     fn part1_ex1() {
-        assert_eq!(part1(&input_generator(EXAMPLE_INPUT)), 37);
-        assert_eq!(part1_parallel(&input_generator(EXAMPLE_INPUT)), 37);
-        assert_eq!(part1_memo(&input_generator(EXAMPLE_INPUT)), 37);
-        assert_eq!(part1_stats(&input_generator(EXAMPLE_INPUT)), 37);
+        assert_eq!(part1(&input_generator(EXAMPLE_INPUT)), Ok(37));
+        assert_eq!(part1_parallel(&input_generator(EXAMPLE_INPUT)), Ok(37));
+        assert_eq!(part1_memo(&input_generator(EXAMPLE_INPUT)), Ok(37));
+        assert_eq!(part1_stats(&input_generator(EXAMPLE_INPUT)), Ok(37));
+        assert_eq!(part1_convex(&input_generator(EXAMPLE_INPUT)), Ok(37));
     }
 
     #[test]
     fn part2_ex1() {
-        assert_eq!(part2(&input_generator(EXAMPLE_INPUT)), 168);
-        assert_eq!(part2_parallel(&input_generator(EXAMPLE_INPUT)), 168);
-        assert_eq!(part2_memo(&input_generator(EXAMPLE_INPUT)), 168);
-        assert_eq!(part2_stats(&input_generator(EXAMPLE_INPUT)), 168);
+        assert_eq!(part2(&input_generator(EXAMPLE_INPUT)), Ok(168));
+        assert_eq!(part2_parallel(&input_generator(EXAMPLE_INPUT)), Ok(168));
+        assert_eq!(part2_memo(&input_generator(EXAMPLE_INPUT)), Ok(168));
+        assert_eq!(part2_stats(&input_generator(EXAMPLE_INPUT)), Ok(168));
+        assert_eq!(part2_convex(&input_generator(EXAMPLE_INPUT)), Ok(168));
+    }
+
+    #[test]
+    fn cost_functions() {
+        let crabs = crabs(&input_generator(EXAMPLE_INPUT));
+        assert_eq!(align(&crabs, &Linear).unwrap().positions, 2..=2);
+        assert_eq!(align(&crabs, &Triangular).unwrap().positions, 5..=5);
+        let quadratic = align(&crabs, &Quadratic).unwrap();
+        assert_eq!(quadratic.positions, 5..=5);
+        assert_eq!(quadratic.fuel, total_fuel(&crabs, &Quadratic, 5).unwrap());
+
+        // Anywhere between two crabs is as good as anywhere else
+        let pair = [Crab::from(0), Crab::from(10)];
+        assert_eq!(
+            align(&pair, &Linear),
+            Ok(Alignment {
+                positions: 0..=10,
+                fuel: 10
+            })
+        );
+        assert_eq!(align_fast(&pair, &Linear), align(&pair, &Linear));
+        // First three steps are free
+        let free = |d: u32| (d as i128 - 3).max(0);
+        assert_eq!(align(&pair, &free).unwrap().positions, 3..=7);
+        assert_eq!(align(&[], &Linear), Err(FuelError::NoCrabs));
+    }
+
+    #[test]
+    fn weighted() {
+        // A heavy crab drags the median and the mean towards it
+        let mut crabs = crabs(&input_generator(EXAMPLE_INPUT));
+        crabs[0].weight = 20;
+        assert_eq!(align(&crabs, &Linear).unwrap().positions, 16..=16);
+        let mut rng = Lcg::new(3);
+        let mut next = |n: u64| rng.below(n);
+        for _ in 0..200 {
+            let crabs: Vec<Crab> = (0..1 + next(12))
+                .map(|_| Crab {
+                    pos: next(1000) as i32 - 500,
+                    weight: next(50) as u32,
+                })
+                .collect();
+            if crabs.iter().all(|c| c.weight == 0) {
+                continue;
+            }
+            assert_eq!(align_fast(&crabs, &Linear), align(&crabs, &Linear));
+            assert_eq!(align_fast(&crabs, &Triangular), align(&crabs, &Triangular));
+            assert_eq!(align_fast(&crabs, &Quadratic), align(&crabs, &Quadratic));
+        }
+    }
+
+    #[test]
+    fn big_numbers() {
+        // Over i32 for both the distance cost and the total
+        let far = [0, 100_000];
+        assert_eq!(part2(&far), Ok(2 * 50_000 * 50_001 / 2));
+        assert_eq!(part2_stats(&far), part2(&far));
+        // Over i64 for the total
+        let spread: Vec<i32> = [-2_000_000_000; 10]
+            .into_iter()
+            .chain([2_000_000_000; 10])
+            .collect();
+        let fuel = 20 * (2_000_000_000 * 2_000_000_001 / 2);
+        assert_eq!(part2_stats(&spread), Ok(fuel));
+        assert_eq!(part2_convex(&spread), Ok(fuel));
+        assert_eq!(part1_stats(&spread), Ok(20 * 2_000_000_000));
+        assert_eq!(part1_stats(&[]), Err(FuelError::NoCrabs));
+        assert_eq!(part2(&[]), Err(FuelError::NoCrabs));
+        let crabs = [
+            Crab {
+                pos: i32::MIN,
+                weight: u32::MAX,
+            },
+            Crab::from(i32::MAX),
+        ];
+        assert_eq!(
+            total_fuel(&crabs, &Triangular, i32::MAX).unwrap(),
+            u32::MAX as i128 * (u32::MAX as i128 * (u32::MAX as i128 + 1) / 2)
+        );
+        let huge = |d: u32| i128::MAX / 2 + d as i128;
+        assert_eq!(
+            total_fuel(&crabs, &huge, 0),
+            Err(FuelError::Overflow { target: 0 })
+        );
+        assert!(align(&crabs, &huge).is_err());
     }
 }
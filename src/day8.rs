@@ -1,5 +1,6 @@
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 type Chunk = (Vec<String>, Vec<String>);
 
@@ -226,6 +227,316 @@ pub fn part2_intersect(input: &[Chunk]) -> u32 {
         .sum()
 }
 
+////////////////////////////////////////////////////////////////////////
+// General solver: which wire drives which segment, for any font
+//
+
+// Segments a-g are top, top left, top right, middle, bottom left, bottom
+// right and bottom. Patterns are bitmasks over them, a lowest.
+pub const SEGMENTS: usize = 7;
+
+// None if the pattern names a wire past g
+fn mask(pattern: &str) -> Option<u8> {
+    pattern.bytes().try_fold(0, |m, b| match b {
+        b'a'..=b'g' => Some(m | 1 << (b - b'a')),
+        _ => None,
+    })
+}
+
+const DIGITS: [(char, &str); 10] = [
+    ('0', "abcefg"),
+    ('1', "cf"),
+    ('2', "acdeg"),
+    ('3', "acdfg"),
+    ('4', "bcdf"),
+    ('5', "abdfg"),
+    ('6', "abdefg"),
+    ('7', "acf"),
+    ('8', "abcdefg"),
+    ('9', "abcdfg"),
+];
+
+const HEX_LETTERS: [(char, &str); 6] = [
+    ('A', "abcdef"),
+    ('b', "bdefg"),
+    ('C', "abeg"),
+    ('d', "cdefg"),
+    ('E', "abdeg"),
+    ('F', "abde"),
+];
+
+// The symbols a display can show, and the segments lit for each
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Font {
+    glyphs: Vec<(char, u8)>,
+}
+
+impl Font {
+    // Glyphs must all light different segments, each one of a to g
+    pub fn new(glyphs: &[(char, &str)]) -> Font {
+        let glyphs: Vec<(char, u8)> = glyphs
+            .iter()
+            .map(|&(c, segs)| {
+                let m = mask(segs).unwrap_or_else(|| panic!("{:?} lights unknown segments", c));
+                (c, m)
+            })
+            .collect();
+        assert!(
+            glyphs.iter().map(|&(_, m)| m).all_unique(),
+            "two glyphs look the same"
+        );
+        Font { glyphs }
+    }
+
+    pub fn decimal() -> Font {
+        Font::new(&DIGITS)
+    }
+
+    // Decimal plus A b C d E F
+    pub fn hex() -> Font {
+        Font::new(&[DIGITS.as_slice(), &HEX_LETTERS].concat())
+    }
+
+    pub fn glyph(&self, segments: u8) -> Option<char> {
+        self.glyphs
+            .iter()
+            .find(|&&(_, m)| m == segments)
+            .map(|&(c, _)| c)
+    }
+}
+
+// The segment each wire lights
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Wiring(pub [u8; SEGMENTS]);
+
+impl Wiring {
+    fn apply(&self, wires: u8) -> u8 {
+        (0..SEGMENTS)
+            .filter(|&w| wires & 1 << w != 0)
+            .fold(0, |m, w| m | 1 << self.0[w])
+    }
+
+    pub fn read(&self, font: &Font, pattern: &str) -> Option<char> {
+        font.glyph(self.apply(mask(pattern)?))
+    }
+
+    // The wire driving each segment
+    pub fn inverse(&self) -> Wiring {
+        let mut inv = [0; SEGMENTS];
+        for (w, &seg) in self.0.iter().enumerate() {
+            inv[seg as usize] = w as u8;
+        }
+        Wiring(inv)
+    }
+
+    // The wires lit to show segments
+    fn scramble(&self, segments: u8) -> String {
+        let inv = self.inverse();
+        let wires = inv.apply(segments);
+        (0..SEGMENTS)
+            .filter(|&w| wires & 1 << w != 0)
+            .map(|w| (b'a' + w as u8) as char)
+            .collect()
+    }
+}
+
+// A puzzle line showing output on a display wired up this way. Every glyph
+// appears once in the signal, sorted so their order gives nothing away.
+pub fn encode(font: &Font, wiring: &Wiring, output: &str) -> String {
+    let mut signal: Vec<String> = font
+        .glyphs
+        .iter()
+        .map(|&(_, g)| wiring.scramble(g))
+        .collect();
+    signal.sort();
+    let shown: Vec<String> = output
+        .chars()
+        .map(|c| {
+            let &(_, g) = font
+                .glyphs
+                .iter()
+                .find(|&&(g, _)| g == c)
+                .unwrap_or_else(|| panic!("no glyph for {:?}", c));
+            wiring.scramble(g)
+        })
+        .collect();
+    format!("{} | {}", signal.join(" "), shown.join(" "))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    Unique(Wiring),
+    // More than one wiring explains what was seen
+    Ambiguous(Vec<Wiring>),
+    // Nothing does
+    Contradiction,
+}
+
+const ALL: u8 = (1 << SEGMENTS) - 1;
+
+// Narrow down the segments each wire could drive until nothing changes.
+// Each pattern must show a glyph with as many segments, so its wires can
+// only drive segments of those glyphs, and the rest only segments outside
+// them. A wire known for certain can't be shared.
+fn propagate(font: &Font, patterns: &[u8]) -> Option<[u8; SEGMENTS]> {
+    let mut domains = [ALL; SEGMENTS];
+    loop {
+        let before = domains;
+        for &p in patterns {
+            let fits = |&&(_, g): &&(char, u8)| {
+                g.count_ones() == p.count_ones()
+                    && (0..SEGMENTS).all(|w| {
+                        let allowed = if p & 1 << w != 0 { g } else { !g };
+                        domains[w] & allowed != 0
+                    })
+            };
+            let (inside, outside) = font
+                .glyphs
+                .iter()
+                .filter(fits)
+                .fold((0, 0), |(i, o), &(_, g)| (i | g, o | !g & ALL));
+            for (w, domain) in domains.iter_mut().enumerate() {
+                *domain &= if p & 1 << w != 0 { inside } else { outside };
+            }
+        }
+        for w in 0..SEGMENTS {
+            let known = domains[w];
+            if known.count_ones() == 1 {
+                for (v, domain) in domains.iter_mut().enumerate() {
+                    if v != w {
+                        *domain &= !known;
+                    }
+                }
+            }
+        }
+        if domains.contains(&0) {
+            return None;
+        }
+        if domains == before {
+            return Some(domains);
+        }
+    }
+}
+
+// Every wiring under which all the patterns are glyphs of the font
+pub fn wirings(font: &Font, patterns: &[String]) -> Vec<Wiring> {
+    let patterns: Vec<u8> = match patterns.iter().map(|p| mask(p)).collect() {
+        Some(p) => p,
+        None => return vec![],
+    };
+    let domains = match propagate(font, &patterns) {
+        Some(d) => d,
+        None => return vec![],
+    };
+
+    // Try what's left, wire by wire
+    fn search(
+        font: &Font,
+        patterns: &[u8],
+        domains: &[u8; SEGMENTS],
+        wiring: &mut [u8; SEGMENTS],
+        w: usize,
+        used: u8,
+        found: &mut Vec<Wiring>,
+    ) {
+        if w == SEGMENTS {
+            let wiring = Wiring(*wiring);
+            if patterns
+                .iter()
+                .all(|&p| font.glyph(wiring.apply(p)).is_some())
+            {
+                found.push(wiring);
+            }
+            return;
+        }
+        for seg in 0..SEGMENTS as u8 {
+            if domains[w] & 1 << seg != 0 && used & 1 << seg == 0 {
+                wiring[w] = seg;
+                search(
+                    font,
+                    patterns,
+                    domains,
+                    wiring,
+                    w + 1,
+                    used | 1 << seg,
+                    found,
+                );
+            }
+        }
+    }
+
+    let mut found = vec![];
+    search(
+        font,
+        &patterns,
+        &domains,
+        &mut [0; SEGMENTS],
+        0,
+        0,
+        &mut found,
+    );
+    found
+}
+
+pub fn solve(font: &Font, patterns: &[String]) -> Solution {
+    let mut all = wirings(font, patterns);
+    match all.len() {
+        0 => Solution::Contradiction,
+        1 => Solution::Unique(all.pop().unwrap()),
+        _ => Solution::Ambiguous(all),
+    }
+}
+
+// Why a display couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    // The signal is explained by this many wirings rather than exactly one
+    Unsolved { signal: Vec<String>, wirings: usize },
+    // An output pattern isn't a digit under the wiring
+    Unreadable(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Unsolved { signal, wirings } => {
+                write!(f, "{} wirings explain {}", wirings, signal.join(" "))
+            }
+            DecodeError::Unreadable(pattern) => write!(f, "{} isn't a digit", pattern),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[aoc(day8, part2, solver)]
+pub fn part2_solver(input: &[Chunk]) -> Result<u32, DecodeError> {
+    let font = Font::decimal();
+    input
+        .iter()
+        .map(|(signal, output)| {
+            let wiring = match solve(&font, signal) {
+                Solution::Unique(w) => w,
+                other => {
+                    return Err(DecodeError::Unsolved {
+                        signal: signal.clone(),
+                        wirings: match other {
+                            Solution::Ambiguous(all) => all.len(),
+                            _ => 0,
+                        },
+                    })
+                }
+            };
+            output.iter().try_fold(0, |n, o| {
+                let d = wiring
+                    .read(&font, o)
+                    .ok_or_else(|| DecodeError::Unreadable(o.clone()))?;
+                Ok(n * 10 + d.to_digit(10).unwrap())
+            })
+        })
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -250,6 +561,96 @@ gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce
     #[test]
     fn part2_ex1() {
         assert_eq!(part2(&input_generator(EXAMPLE_INPUT)), 61229);
-        assert_eq!(part2_intersect(&input_generator(EXAMPLE_INPUT)), 61229)
+        assert_eq!(part2_intersect(&input_generator(EXAMPLE_INPUT)), 61229);
+        assert_eq!(part2_solver(&input_generator(EXAMPLE_INPUT)), Ok(61229))
+    }
+
+    fn patterns(ps: &[&str]) -> Vec<String> {
+        ps.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn general_solver() {
+        let decimal = Font::decimal();
+        let (signal, _) = &input_generator(EXAMPLE_INPUT)[0];
+        assert!(matches!(solve(&decimal, signal), Solution::Unique(_)));
+
+        // Only a 1 and a 7 seen: a is pinned down, c and f could be either
+        // way round and the other four wires anything
+        let few = patterns(&["ab", "abd"]);
+        match solve(&decimal, &few) {
+            Solution::Ambiguous(all) => {
+                assert_eq!(all.len(), 2 * 24);
+                assert!(all.iter().all(|w| w.0[3] == 0));
+            }
+            other => panic!("{:?}", other),
+        }
+        // No decimal digit has a single segment
+        assert_eq!(solve(&decimal, &patterns(&["a"])), Solution::Contradiction);
+        // Wires past g, or not letters at all
+        assert_eq!(
+            solve(&decimal, &patterns(&["ab", "abz"])),
+            Solution::Contradiction
+        );
+        assert_eq!(
+            solve(&decimal, &patterns(&["A", "1"])),
+            Solution::Contradiction
+        );
+        assert_eq!(Wiring([0, 1, 2, 3, 4, 5, 6]).read(&decimal, "cfh"), None);
+
+        // The solver reports what it can't decode rather than giving up
+        let lines = input_generator("ab abd | ab\nab abd abcdefgh | ab\n");
+        assert_eq!(
+            part2_solver(&lines[..1]),
+            Err(DecodeError::Unsolved {
+                signal: few.clone(),
+                wirings: 48
+            })
+        );
+        assert!(matches!(
+            part2_solver(&lines[1..]),
+            Err(DecodeError::Unsolved { wirings: 0, .. })
+        ));
+
+        // All sixteen hex digits through a scrambled harness
+        let hex = Font::hex();
+        let harness = Wiring([3, 6, 0, 5, 1, 4, 2]);
+        let (seen, shown) = &input_generator(&encode(&hex, &harness, "bEEF"))[0];
+        assert_eq!(seen.len(), 16);
+        assert_eq!(solve(&hex, seen), Solution::Unique(harness));
+        let read: String = shown
+            .iter()
+            .map(|o| harness.read(&hex, o).unwrap())
+            .collect();
+        assert_eq!(read, "bEEF");
+    }
+
+    #[test]
+    fn round_trip() {
+        let decimal = Font::decimal();
+        let line = encode(&decimal, &Wiring([0, 1, 2, 3, 4, 5, 6]), "0189");
+        assert_eq!(
+            line,
+            "abcdefg abcdfg abcefg abdefg abdfg acdeg acdfg acf bcdf cf | abcefg cf abcdefg abcdfg"
+        );
+
+        // Every possible wiring, each showing a different number
+        let solvers: [fn(&[Chunk]) -> u32; 3] =
+            [part2, part2_intersect, |c| part2_solver(c).unwrap()];
+        for (i, perm) in (0..SEGMENTS as u8).permutations(SEGMENTS).enumerate() {
+            let mut wiring = [0; SEGMENTS];
+            wiring.copy_from_slice(&perm);
+            let wiring = Wiring(wiring);
+            let value = (i * 7919) % 10000;
+            let chunks = input_generator(&encode(&decimal, &wiring, &format!("{:04}", value)));
+            for solve in solvers {
+                assert_eq!(solve(&chunks), value as u32, "wiring {:?}", wiring);
+            }
+            let easy = format!("{:04}", value)
+                .chars()
+                .filter(|c| "1478".contains(*c))
+                .count();
+            assert_eq!(part1(&chunks), easy as u32);
+        }
     }
 }
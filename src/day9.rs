@@ -1,5 +1,4 @@
-use std::collections::HashSet;
-use std::collections::VecDeque;
+use crate::search;
 
 // const ROWS: usize = 100;
 // const COLS: usize = 100;
@@ -12,7 +11,7 @@ type Point = (usize, usize);
 
 fn parse_input(input: &str) -> Grid {
     let mut grid = [[0_u8; ROWS]; COLS];
-    for (r, row) in input.trim().split_whitespace().enumerate() {
+    for (r, row) in input.split_whitespace().enumerate() {
         // println!("{}, {:?}", r, row);
         for (c, ch) in row.trim().bytes().enumerate() {
             // println!("{}, {} = {}", r, c, ch);
@@ -62,25 +61,16 @@ fn neighbors_of(p: Point) -> Vec<Point> {
 }
 
 fn basin_size(grid: Grid, low: Point) -> u32 {
-    let mut marked: HashSet<Point> = HashSet::new();
-    let mut todo: VecDeque<Point> = VecDeque::new();
-
-    todo.push_back(low);
-
-    while !todo.is_empty() {
-        let (x, y) = todo.pop_front().unwrap();
-        marked.insert((x, y));
-        // Not 9 and not marked? add it to the size of this basin
-        for (nx, ny) in neighbors_of((x, y))
-            .iter()
-            .filter(|&p| !marked.contains(p))
-            .filter(|(xx, yy)| grid[*xx][*yy] != 9)
-        {
-            todo.push_back((*nx, *ny));
-        }
-    }
-
-    marked.len() as u32
+    // Not 9? it's part of the same basin
+    let basin = |p: &Point| {
+        neighbors_of(*p)
+            .into_iter()
+            .filter(|&(x, y)| grid[x][y] != 9)
+            .map(|n| (n, 1))
+            .collect()
+    };
+
+    search::bfs(&basin, low).len() as u32
 }
 
 #[aoc(day9, part1)]
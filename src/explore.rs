@@ -0,0 +1,231 @@
+use std::io::{self, Write};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::{cursor, execute, terminal};
+
+use crate::{day11, day20, day25};
+
+// A puzzle that runs one step at a time, for stepping through interactively
+pub trait Simulation {
+    fn step(&mut self);
+    fn render(&self) -> String;
+    // Named running totals, shown under the grid
+    fn counters(&self) -> Vec<(&'static str, usize)>;
+    fn snapshot(&self) -> Box<dyn Simulation>;
+}
+
+// The simulation for a day, if it has one
+pub fn simulation(day: u32, input: &str) -> Option<Box<dyn Simulation>> {
+    match day {
+        11 => Some(Box::new(day11::Octopuses::new(input))),
+        20 => Some(Box::new(day20::Enhancement::new(input))),
+        25 => Some(Box::new(day25::Trench::new(input))),
+        _ => None,
+    }
+}
+
+// Keep at most this many saved states, however far the simulation has run
+const MAX_CHECKPOINTS: usize = 256;
+
+// Saved states every interval steps, so going back replays at most interval
+// steps. When there are too many, every other one goes and the interval
+// doubles.
+pub struct Explorer {
+    checkpoints: Vec<(usize, Box<dyn Simulation>)>,
+    interval: usize,
+    current: Box<dyn Simulation>,
+    at: usize,
+}
+
+impl Explorer {
+    pub fn new(sim: Box<dyn Simulation>) -> Explorer {
+        Explorer {
+            checkpoints: vec![(0, sim.snapshot())],
+            interval: 16,
+            current: sim,
+            at: 0,
+        }
+    }
+
+    pub fn step_number(&self) -> usize {
+        self.at
+    }
+
+    pub fn current(&self) -> &dyn Simulation {
+        self.current.as_ref()
+    }
+
+    pub fn forward(&mut self) {
+        self.jump(self.at + 1);
+    }
+
+    pub fn back(&mut self) {
+        self.jump(self.at.saturating_sub(1));
+    }
+
+    pub fn jump(&mut self, step: usize) {
+        if step < self.at {
+            // The first checkpoint is step 0, so there's always one to go back to
+            let (at, sim) = self
+                .checkpoints
+                .iter()
+                .rev()
+                .find(|(s, _)| *s <= step)
+                .unwrap();
+            self.current = sim.snapshot();
+            self.at = *at;
+        }
+        while self.at < step {
+            self.current.step();
+            self.at += 1;
+            let last = self.checkpoints.last().unwrap().0;
+            if self.at == last + self.interval {
+                self.checkpoints.push((self.at, self.current.snapshot()));
+                if self.checkpoints.len() > MAX_CHECKPOINTS {
+                    self.interval *= 2;
+                    let interval = self.interval;
+                    self.checkpoints.retain(|(s, _)| s % interval == 0);
+                }
+            }
+        }
+    }
+
+    pub fn screen(&self) -> String {
+        let sim = self.current();
+        let mut out = format!("Step {}\n\n{}\n", self.at, sim.render());
+        for (name, value) in sim.counters() {
+            out += &format!("{}: {}\n", name, value);
+        }
+        out
+    }
+}
+
+const HELP: &str = "[n/→] next  [p/←] previous  [g] go to step  [q] quit";
+
+fn draw(out: &mut impl Write, explorer: &Explorer, prompt: &str) -> io::Result<()> {
+    execute!(
+        out,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(0, 0)
+    )?;
+    // Raw mode doesn't translate newlines
+    for line in explorer.screen().lines() {
+        write!(out, "{}\r\n", line)?;
+    }
+    write!(out, "\r\n{}\r\n{}", HELP, prompt)?;
+    out.flush()
+}
+
+// Read a step number typed after pressing g
+fn read_step(out: &mut impl Write, explorer: &Explorer) -> io::Result<Option<usize>> {
+    let mut typed = String::new();
+    loop {
+        draw(out, explorer, &format!("Go to step: {}", typed))?;
+        if let Event::Key(KeyEvent { code, .. }) = event::read()? {
+            match code {
+                KeyCode::Char(c) if c.is_ascii_digit() => typed.push(c),
+                KeyCode::Backspace => {
+                    typed.pop();
+                }
+                KeyCode::Enter => return Ok(typed.parse().ok()),
+                KeyCode::Esc => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn event_loop(out: &mut impl Write, explorer: &mut Explorer) -> io::Result<()> {
+    loop {
+        draw(out, explorer, "")?;
+        if let Event::Key(KeyEvent { code, modifiers }) = event::read()? {
+            match code {
+                KeyCode::Char('n') | KeyCode::Right | KeyCode::Char(' ') => explorer.forward(),
+                KeyCode::Char('p') | KeyCode::Left => explorer.back(),
+                KeyCode::Char('g') => {
+                    if let Some(step) = read_step(out, explorer)? {
+                        explorer.jump(step);
+                    }
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+}
+
+// Take over the terminal until the user quits
+pub fn run(sim: Box<dyn Simulation>) -> io::Result<()> {
+    let mut explorer = Explorer::new(sim);
+    let mut out = io::stdout();
+
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let res = event_loop(&mut out, &mut explorer);
+    execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Counter(usize);
+
+    impl Simulation for Counter {
+        fn step(&mut self) {
+            self.0 += 1;
+        }
+
+        fn render(&self) -> String {
+            "#".repeat(self.0)
+        }
+
+        fn counters(&self) -> Vec<(&'static str, usize)> {
+            vec![("count", self.0)]
+        }
+
+        fn snapshot(&self) -> Box<dyn Simulation> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn step_forward_and_back() {
+        let mut ex = Explorer::new(Box::new(Counter(0)));
+        ex.jump(5);
+        assert_eq!(ex.current().counters(), vec![("count", 5)]);
+        ex.back();
+        ex.back();
+        assert_eq!(ex.step_number(), 3);
+        assert_eq!(ex.screen(), "Step 3\n\n###\ncount: 3\n");
+        ex.forward();
+        assert_eq!(ex.current().render(), "####");
+    }
+
+    #[test]
+    fn long_jumps() {
+        let mut ex = Explorer::new(Box::new(Counter(0)));
+        ex.jump(100_000);
+        assert!(ex.checkpoints.len() <= MAX_CHECKPOINTS);
+        ex.back();
+        assert_eq!(ex.current().counters(), vec![("count", 99_999)]);
+        ex.jump(17);
+        assert_eq!(ex.current().counters(), vec![("count", 17)]);
+        ex.jump(0);
+        assert_eq!(ex.screen(), "Step 0\n\n\ncount: 0\n");
+    }
+
+    #[test]
+    fn day11_flashes() {
+        let day11 = "5483143223\n2745854711\n5264556173\n6141336146\n6357385478\n\
+                     4167524645\n2176841721\n6882881134\n4846848554\n5283751526\n";
+        let mut ex = Explorer::new(simulation(11, day11).unwrap());
+        ex.jump(100);
+        assert!(ex.current().counters().contains(&("flashes", 1656)));
+    }
+}
@@ -0,0 +1,622 @@
+use std::cmp::{max, min};
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point2 {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point2 {
+    pub const fn new(x: i32, y: i32) -> Point2 {
+        Point2 { x, y }
+    }
+
+    pub fn manhattan(&self, other: Point2) -> u32 {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+
+    pub fn chebyshev(&self, other: Point2) -> u32 {
+        max(self.x.abs_diff(other.x), self.y.abs_diff(other.y))
+    }
+
+    // Each coordinate clamped to -1, 0 or 1
+    pub fn signum(&self) -> Point2 {
+        Point2::new(self.x.signum(), self.y.signum())
+    }
+
+    // Up, right, down, left, with y growing downwards
+    pub fn orthogonal(&self) -> [Point2; 4] {
+        let Point2 { x, y } = *self;
+        [
+            Point2::new(x, y - 1),
+            Point2::new(x + 1, y),
+            Point2::new(x, y + 1),
+            Point2::new(x - 1, y),
+        ]
+    }
+
+    // All 8 surrounding points, in reading order
+    pub fn surrounding(&self) -> [Point2; 8] {
+        let Point2 { x, y } = *self;
+        [
+            Point2::new(x - 1, y - 1),
+            Point2::new(x, y - 1),
+            Point2::new(x + 1, y - 1),
+            Point2::new(x - 1, y),
+            Point2::new(x + 1, y),
+            Point2::new(x - 1, y + 1),
+            Point2::new(x, y + 1),
+            Point2::new(x + 1, y + 1),
+        ]
+    }
+}
+
+impl From<(i32, i32)> for Point2 {
+    fn from((x, y): (i32, i32)) -> Point2 {
+        Point2::new(x, y)
+    }
+}
+
+impl From<Point2> for (i32, i32) {
+    fn from(p: Point2) -> (i32, i32) {
+        (p.x, p.y)
+    }
+}
+
+impl Add for Point2 {
+    type Output = Point2;
+    fn add(self, o: Point2) -> Point2 {
+        Point2::new(self.x + o.x, self.y + o.y)
+    }
+}
+
+impl AddAssign for Point2 {
+    fn add_assign(&mut self, o: Point2) {
+        *self = *self + o;
+    }
+}
+
+impl Sub for Point2 {
+    type Output = Point2;
+    fn sub(self, o: Point2) -> Point2 {
+        Point2::new(self.x - o.x, self.y - o.y)
+    }
+}
+
+impl SubAssign for Point2 {
+    fn sub_assign(&mut self, o: Point2) {
+        *self = *self - o;
+    }
+}
+
+impl Neg for Point2 {
+    type Output = Point2;
+    fn neg(self) -> Point2 {
+        Point2::new(-self.x, -self.y)
+    }
+}
+
+impl Mul<i32> for Point2 {
+    type Output = Point2;
+    fn mul(self, k: i32) -> Point2 {
+        Point2::new(self.x * k, self.y * k)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point3 {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+// The difference between two Point3s
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Vec3 {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Point3 {
+    pub const ORIGIN: Point3 = Point3::new(0, 0, 0);
+
+    pub const fn new(x: i32, y: i32, z: i32) -> Point3 {
+        Point3 { x, y, z }
+    }
+
+    pub fn manhattan(&self, other: Point3) -> u32 {
+        (*self - other).manhattan()
+    }
+
+    pub fn chebyshev(&self, other: Point3) -> u32 {
+        (*self - other).chebyshev()
+    }
+
+    // The displacement from the origin to here
+    pub fn to_vec(self) -> Vec3 {
+        self - Point3::ORIGIN
+    }
+}
+
+impl Vec3 {
+    pub const fn new(x: i32, y: i32, z: i32) -> Vec3 {
+        Vec3 { x, y, z }
+    }
+
+    pub fn manhattan(&self) -> u32 {
+        self.x.unsigned_abs() + self.y.unsigned_abs() + self.z.unsigned_abs()
+    }
+
+    pub fn chebyshev(&self) -> u32 {
+        max(
+            self.x.unsigned_abs(),
+            max(self.y.unsigned_abs(), self.z.unsigned_abs()),
+        )
+    }
+
+    pub fn dot(&self, o: Vec3) -> i64 {
+        self.x as i64 * o.x as i64 + self.y as i64 * o.y as i64 + self.z as i64 * o.z as i64
+    }
+
+    pub fn cross(&self, o: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * o.z - self.z * o.y,
+            self.z * o.x - self.x * o.z,
+            self.x * o.y - self.y * o.x,
+        )
+    }
+}
+
+impl From<(i32, i32, i32)> for Point3 {
+    fn from((x, y, z): (i32, i32, i32)) -> Point3 {
+        Point3::new(x, y, z)
+    }
+}
+
+impl From<Point3> for (i32, i32, i32) {
+    fn from(p: Point3) -> (i32, i32, i32) {
+        (p.x, p.y, p.z)
+    }
+}
+
+impl From<(i32, i32, i32)> for Vec3 {
+    fn from((x, y, z): (i32, i32, i32)) -> Vec3 {
+        Vec3::new(x, y, z)
+    }
+}
+
+impl Sub for Point3 {
+    type Output = Vec3;
+    fn sub(self, o: Point3) -> Vec3 {
+        Vec3::new(self.x - o.x, self.y - o.y, self.z - o.z)
+    }
+}
+
+impl Add<Vec3> for Point3 {
+    type Output = Point3;
+    fn add(self, v: Vec3) -> Point3 {
+        Point3::new(self.x + v.x, self.y + v.y, self.z + v.z)
+    }
+}
+
+impl AddAssign<Vec3> for Point3 {
+    fn add_assign(&mut self, v: Vec3) {
+        *self = *self + v;
+    }
+}
+
+impl Sub<Vec3> for Point3 {
+    type Output = Point3;
+    fn sub(self, v: Vec3) -> Point3 {
+        self + -v
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Vec3;
+    fn add(self, o: Vec3) -> Vec3 {
+        Vec3::new(self.x + o.x, self.y + o.y, self.z + o.z)
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Vec3;
+    fn sub(self, o: Vec3) -> Vec3 {
+        self + -o
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Vec3;
+    fn neg(self) -> Vec3 {
+        Vec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<i32> for Vec3 {
+    type Output = Vec3;
+    fn mul(self, k: i32) -> Vec3 {
+        Vec3::new(self.x * k, self.y * k, self.z * k)
+    }
+}
+
+// A proper rotation (no reflection) by multiples of 90 degrees, stored as a
+// signed permutation matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rotation([[i32; 3]; 3]);
+
+impl Rotation {
+    pub const IDENTITY: Rotation = Rotation([[1, 0, 0], [0, 1, 0], [0, 0, 1]]);
+
+    // All 24 ways of facing an axis and picking which way is up
+    pub fn all() -> Vec<Rotation> {
+        let mut rotations = vec![];
+        for perm in [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ] {
+            for signs in 0..8 {
+                let mut m = [[0; 3]; 3];
+                for (row, &col) in perm.iter().enumerate() {
+                    m[row][col] = if signs & (1 << row) == 0 { 1 } else { -1 };
+                }
+                let r = Rotation(m);
+                if r.determinant() == 1 {
+                    rotations.push(r);
+                }
+            }
+        }
+        rotations
+    }
+
+    fn determinant(&self) -> i32 {
+        let m = self.0;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    pub fn apply(&self, v: Vec3) -> Vec3 {
+        let m = self.0;
+        let row = |r: [i32; 3]| r[0] * v.x + r[1] * v.y + r[2] * v.z;
+        Vec3::new(row(m[0]), row(m[1]), row(m[2]))
+    }
+
+    // Rotate a point about the origin
+    pub fn apply_point(&self, p: Point3) -> Point3 {
+        Point3::ORIGIN + self.apply(p.to_vec())
+    }
+
+    // self after other
+    pub fn compose(&self, other: Rotation) -> Rotation {
+        let (a, b) = (self.0, other.0);
+        let mut m = [[0; 3]; 3];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, cell) in row.iter_mut().enumerate() {
+                *cell = (0..3).map(|k| a[r][k] * b[k][c]).sum();
+            }
+        }
+        Rotation(m)
+    }
+
+    // Rotation matrices are orthogonal, so the inverse is the transpose
+    pub fn inverse(&self) -> Rotation {
+        let m = self.0;
+        let mut t = [[0; 3]; 3];
+        for (r, row) in m.iter().enumerate() {
+            for (c, &cell) in row.iter().enumerate() {
+                t[c][r] = cell;
+            }
+        }
+        Rotation(t)
+    }
+}
+
+// Axis-aligned rectangle, both corners inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Box2 {
+    pub min: Point2,
+    pub max: Point2,
+}
+
+impl Box2 {
+    pub fn new(a: Point2, b: Point2) -> Box2 {
+        Box2 {
+            min: Point2::new(min(a.x, b.x), min(a.y, b.y)),
+            max: Point2::new(max(a.x, b.x), max(a.y, b.y)),
+        }
+    }
+
+    pub fn contains(&self, p: Point2) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x) && (self.min.y..=self.max.y).contains(&p.y)
+    }
+
+    pub fn intersection(&self, o: &Box2) -> Option<Box2> {
+        let lo = Point2::new(max(self.min.x, o.min.x), max(self.min.y, o.min.y));
+        let hi = Point2::new(min(self.max.x, o.max.x), min(self.max.y, o.max.y));
+        if lo.x > hi.x || lo.y > hi.y {
+            None
+        } else {
+            Some(Box2 { min: lo, max: hi })
+        }
+    }
+
+    // Number of lattice points covered
+    pub fn area(&self) -> u64 {
+        (self.max.x.abs_diff(self.min.x) as u64 + 1) * (self.max.y.abs_diff(self.min.y) as u64 + 1)
+    }
+
+    // Smallest box containing every point, if there are any
+    pub fn enclosing(points: impl IntoIterator<Item = Point2>) -> Option<Box2> {
+        points.into_iter().fold(None, |acc: Option<Box2>, p| {
+            Some(match acc {
+                None => Box2 { min: p, max: p },
+                Some(b) => Box2::new(
+                    Point2::new(min(b.min.x, p.x), min(b.min.y, p.y)),
+                    Point2::new(max(b.max.x, p.x), max(b.max.y, p.y)),
+                ),
+            })
+        })
+    }
+}
+
+// Axis-aligned cuboid, both corners inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Box3 {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Box3 {
+    pub fn new(a: Point3, b: Point3) -> Box3 {
+        Box3 {
+            min: Point3::new(min(a.x, b.x), min(a.y, b.y), min(a.z, b.z)),
+            max: Point3::new(max(a.x, b.x), max(a.y, b.y), max(a.z, b.z)),
+        }
+    }
+
+    pub fn contains(&self, p: Point3) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    // Whether other lies entirely inside this box
+    pub fn encloses(&self, other: &Box3) -> bool {
+        self.contains(other.min) && self.contains(other.max)
+    }
+
+    pub fn intersection(&self, o: &Box3) -> Option<Box3> {
+        let lo = Point3::new(
+            max(self.min.x, o.min.x),
+            max(self.min.y, o.min.y),
+            max(self.min.z, o.min.z),
+        );
+        let hi = Point3::new(
+            min(self.max.x, o.max.x),
+            min(self.max.y, o.max.y),
+            min(self.max.z, o.max.z),
+        );
+        if lo.x > hi.x || lo.y > hi.y || lo.z > hi.z {
+            None
+        } else {
+            Some(Box3 { min: lo, max: hi })
+        }
+    }
+
+    // Number of lattice points covered
+    pub fn volume(&self) -> u64 {
+        let d = self.max - self.min;
+        (d.x as u64 + 1) * (d.y as u64 + 1) * (d.z as u64 + 1)
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point3> {
+        let (lo, hi) = (self.min, self.max);
+        (lo.x..=hi.x).flat_map(move |x| {
+            (lo.y..=hi.y).flat_map(move |y| (lo.z..=hi.z).map(move |z| Point3::new(x, y, z)))
+        })
+    }
+}
+
+// Line segment between two lattice points, both ends inclusive
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Segment {
+    pub a: Point2,
+    pub b: Point2,
+}
+
+impl Segment {
+    pub fn new(a: Point2, b: Point2) -> Segment {
+        Segment { a, b }
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        self.a.y == self.b.y
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        self.a.x == self.b.x
+    }
+
+    // Exactly 45 degrees
+    pub fn is_diagonal(&self) -> bool {
+        let d = self.b - self.a;
+        d.x != 0 && d.x.abs() == d.y.abs()
+    }
+
+    pub fn bounds(&self) -> Box2 {
+        Box2::new(self.a, self.b)
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        if self.is_horizontal() {
+            Orientation::Horizontal
+        } else if self.is_vertical() {
+            Orientation::Vertical
+        } else if self.is_diagonal() {
+            Orientation::Diagonal
+        } else {
+            Orientation::Oblique
+        }
+    }
+
+    // Every point with integer coordinates lying exactly on the segment, from
+    // a to b. Steps are the direction divided by gcd(dx, dy).
+    pub fn points(&self) -> impl Iterator<Item = Point2> {
+        let d = self.b - self.a;
+        let n = gcd(d.x.unsigned_abs(), d.y.unsigned_abs()) as i32;
+        let step = if n == 0 {
+            d
+        } else {
+            Point2::new(d.x / n, d.y / n)
+        };
+        let a = self.a;
+        (0..=n).map(move |i| a + step * i)
+    }
+
+    // Every cell the segment passes through, one per step along its longer
+    // axis (Bresenham)
+    pub fn covered(&self) -> Bresenham {
+        let d = self.b - self.a;
+        let dx = d.x.unsigned_abs() as i64;
+        let dy = -(d.y.unsigned_abs() as i64);
+        Bresenham {
+            p: self.a,
+            end: self.b,
+            step: d.signum(),
+            dx,
+            dy,
+            err: dx + dy,
+            done: false,
+        }
+    }
+}
+
+// A point counts as horizontal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+    Diagonal,
+    Oblique,
+}
+
+pub struct Bresenham {
+    p: Point2,
+    end: Point2,
+    step: Point2,
+    dx: i64,
+    dy: i64,
+    err: i64,
+    done: bool,
+}
+
+impl Iterator for Bresenham {
+    type Item = Point2;
+
+    fn next(&mut self) -> Option<Point2> {
+        if self.done {
+            return None;
+        }
+        let p = self.p;
+        if p == self.end {
+            self.done = true;
+        } else {
+            let e2 = 2 * self.err;
+            if e2 >= self.dy {
+                self.err += self.dy;
+                self.p.x += self.step.x;
+            }
+            if e2 <= self.dx {
+                self.err += self.dx;
+                self.p.y += self.step.y;
+            }
+        }
+        Some(p)
+    }
+}
+
+pub fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        let a = Point2::new(1, -2);
+        let b = Point2::new(-3, 5);
+        assert_eq!(a.manhattan(b), 11);
+        assert_eq!(a.chebyshev(b), 7);
+        assert_eq!(
+            Point3::new(1105, -1205, 1229).manhattan(Point3::new(-92, -2380, -20)),
+            3621
+        );
+    }
+
+    #[test]
+    fn rotations() {
+        let all = Rotation::all();
+        assert_eq!(all.len(), 24);
+
+        let v = Vec3::new(1, 2, 3);
+        let mut images = all.iter().map(|r| r.apply(v)).collect::<Vec<_>>();
+        images.sort();
+        images.dedup();
+        assert_eq!(images.len(), 24);
+
+        for r in &all {
+            assert_eq!(r.compose(r.inverse()), Rotation::IDENTITY);
+            assert!(all.contains(&r.compose(all[5])));
+        }
+    }
+
+    #[test]
+    fn boxes() {
+        let a = Box3::new(Point3::new(10, 10, 10), Point3::new(12, 12, 12));
+        let b = Box3::new(Point3::new(11, 11, 11), Point3::new(13, 13, 13));
+        assert_eq!(a.volume(), 27);
+        assert_eq!(a.intersection(&b).unwrap().volume(), 8);
+        assert_eq!(a.points().count(), 27);
+        assert!(a
+            .intersection(&Box3::new(Point3::new(13, 0, 0), Point3::new(20, 20, 20)))
+            .is_none());
+    }
+
+    #[test]
+    fn segments() {
+        let s = Segment::new(Point2::new(8, 0), Point2::new(0, 8));
+        assert!(s.is_diagonal());
+        assert_eq!(s.points().count(), 9);
+        assert_eq!(s.points().last(), Some(Point2::new(0, 8)));
+        assert!(Segment::new(Point2::new(3, 4), Point2::new(1, 4)).is_horizontal());
+
+        // Only the ends and the middle are lattice points, but 7 cells are crossed
+        let s = Segment::new(Point2::new(6, 4), Point2::new(0, 0));
+        assert_eq!(s.orientation(), Orientation::Oblique);
+        assert_eq!(
+            s.points().collect::<Vec<_>>(),
+            vec![Point2::new(6, 4), Point2::new(3, 2), Point2::new(0, 0)]
+        );
+        let cells = s.covered().collect::<Vec<_>>();
+        assert_eq!(cells.len(), 7);
+        assert_eq!(cells[6], Point2::new(0, 0));
+        assert!(cells.windows(2).all(|w| w[0].chebyshev(w[1]) == 1));
+
+        let dot = Segment::new(Point2::new(2, 2), Point2::new(2, 2));
+        assert_eq!(dot.points().count(), 1);
+        assert_eq!(dot.covered().count(), 1);
+    }
+}
@@ -18,10 +18,11 @@ pub mod day15;
 pub mod day16;
 pub mod day17;
 pub mod day18;
-// pub mod day19;
+pub mod day19;
 pub mod day2;
 pub mod day20;
 pub mod day21;
+pub mod day22;
 pub mod day3;
 pub mod day4;
 pub mod day5;
@@ -29,9 +30,17 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
-// pub mod day22;
 // pub mod day23;
 // pub mod day24;
 pub mod day25;
 
+pub mod ca;
+pub mod explore;
+pub mod geom;
+pub mod parse;
+pub mod search;
+
+#[cfg(test)]
+mod rng;
+
 aoc_lib! { year = 2021 }
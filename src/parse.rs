@@ -0,0 +1,392 @@
+use std::fmt;
+use std::ops::{Range, RangeInclusive};
+use std::str::FromStr;
+
+// What we were looking for, and where in the original input it wasn't
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub span: Range<usize>,
+    pub line: usize,
+    pub col: usize,
+    pub expected: String,
+    pub found: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: expected {}, found {:?}",
+            self.line, self.col, self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub type Result<T> = std::result::Result<T, ParseError>;
+
+// A cursor over the puzzle input. Parsers take one of these, consume what they
+// recognise and leave the rest; on failure nothing is consumed.
+#[derive(Debug, Clone, Copy)]
+pub struct Input<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> From<&'a str> for Input<'a> {
+    fn from(src: &'a str) -> Input<'a> {
+        Input::new(src)
+    }
+}
+
+impl<'a> Input<'a> {
+    pub fn new(src: &'a str) -> Input<'a> {
+        Input { src, pos: 0 }
+    }
+
+    // Start part way into src, keeping spans relative to the whole of it
+    fn at(src: &'a str, pos: usize) -> Input<'a> {
+        Input { src, pos }
+    }
+
+    // Everything up to end, as its own input
+    fn until(&self, end: usize) -> Input<'a> {
+        Input::at(&self.src[..end], self.pos)
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    pub fn error(&self, expected: impl Into<String>, len: usize) -> ParseError {
+        let before = &self.src[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let col = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        let mut end = (self.pos + len).min(self.src.len());
+        while !self.src.is_char_boundary(end) {
+            end += 1;
+        }
+        let found = match self.src[self.pos..end].to_owned() {
+            s if s.is_empty() && self.is_empty() => "end of input".to_owned(),
+            s if s.is_empty() => self.rest().lines().next().unwrap_or("").to_owned(),
+            s => s,
+        };
+        ParseError {
+            span: self.pos..end,
+            line,
+            col,
+            expected: expected.into(),
+            found,
+        }
+    }
+
+    pub fn take_while(&mut self, pred: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !pred(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    // Spaces and tabs, but not newlines
+    pub fn skip_ws(&mut self) {
+        self.take_while(|c| c == ' ' || c == '\t');
+    }
+
+    pub fn tag(&mut self, tag: &str) -> Result<()> {
+        if self.rest().starts_with(tag) {
+            self.pos += tag.len();
+            Ok(())
+        } else {
+            Err(self.error(format!("{:?}", tag), tag.len()))
+        }
+    }
+}
+
+// An optionally signed decimal integer
+pub fn int<T: FromStr>(inp: &mut Input) -> Result<T> {
+    let rest = inp.rest();
+    let sign = if rest.starts_with('-') || rest.starts_with('+') {
+        1
+    } else {
+        0
+    };
+    let digits = rest[sign..]
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len() - sign);
+    if digits == 0 {
+        return Err(inp.error("an integer", sign + 1));
+    }
+    match rest[..sign + digits].parse::<T>() {
+        Ok(v) => {
+            inp.pos += sign + digits;
+            Ok(v)
+        }
+        Err(_) => Err(inp.error("an integer in range", sign + digits)),
+    }
+}
+
+// A run of letters, digits and underscores
+pub fn ident<'a>(inp: &mut Input<'a>) -> Result<&'a str> {
+    match inp.take_while(|c| c.is_alphanumeric() || c == '_') {
+        "" => Err(inp.error("an identifier", 1)),
+        s => Ok(s),
+    }
+}
+
+// A single non-whitespace character
+pub fn any_char(inp: &mut Input) -> Result<char> {
+    match inp.peek() {
+        Some(c) if !c.is_whitespace() => {
+            inp.pos += c.len_utf8();
+            Ok(c)
+        }
+        _ => Err(inp.error("a character", 1)),
+    }
+}
+
+// Signed inclusive range written as a..b
+pub fn range<T: FromStr>(inp: &mut Input) -> Result<RangeInclusive<T>> {
+    let start = *inp;
+    let parsed = (|| {
+        let lo = int(inp)?;
+        inp.tag("..")?;
+        let hi = int(inp)?;
+        Ok(lo..=hi)
+    })();
+    if parsed.is_err() {
+        *inp = start;
+    }
+    parsed
+}
+
+// key=value, returning both
+pub fn key_value<'a, K, V>(
+    inp: &mut Input<'a>,
+    mut key: impl FnMut(&mut Input<'a>) -> Result<K>,
+    mut value: impl FnMut(&mut Input<'a>) -> Result<V>,
+) -> Result<(K, V)> {
+    let start = *inp;
+    let parsed = (|| {
+        let k = key(inp)?;
+        inp.tag("=")?;
+        let v = value(inp)?;
+        Ok((k, v))
+    })();
+    if parsed.is_err() {
+        *inp = start;
+    }
+    parsed
+}
+
+// key=value where the key must be exactly name, returning the value
+pub fn named<'a, V>(
+    inp: &mut Input<'a>,
+    name: &str,
+    value: impl FnMut(&mut Input<'a>) -> Result<V>,
+) -> Result<V> {
+    key_value(inp, |i| i.tag(name), value).map(|(_, v)| v)
+}
+
+// One or more items separated by sep. Spaces after a separator are skipped.
+pub fn sep_by<'a, T>(
+    inp: &mut Input<'a>,
+    mut item: impl FnMut(&mut Input<'a>) -> Result<T>,
+    sep: &str,
+) -> Result<Vec<T>> {
+    let mut items = vec![item(inp)?];
+    loop {
+        let before = *inp;
+        if inp.tag(sep).is_err() {
+            return Ok(items);
+        }
+        inp.skip_ws();
+        match item(inp) {
+            Ok(v) => items.push(v),
+            Err(e) => {
+                *inp = before;
+                return Err(e);
+            }
+        }
+    }
+}
+
+// Comma separated key=value pairs, e.g. x=-20..26,y=-36..17
+pub fn key_values<'a, V>(
+    inp: &mut Input<'a>,
+    mut value: impl FnMut(&mut Input<'a>) -> Result<V>,
+) -> Result<Vec<(&'a str, V)>> {
+    sep_by(inp, |i| key_value(i, ident, &mut value), ",")
+}
+
+// Run a parser over the whole of the input, which must all be consumed
+// (trailing whitespace aside).
+pub fn complete<'a, T>(
+    src: &'a str,
+    parser: impl FnOnce(&mut Input<'a>) -> Result<T>,
+) -> Result<T> {
+    let mut inp = Input::new(src);
+    finish(&mut inp, parser, "end of input")
+}
+
+fn finish<'a, T>(
+    inp: &mut Input<'a>,
+    parser: impl FnOnce(&mut Input<'a>) -> Result<T>,
+    end: &str,
+) -> Result<T> {
+    let v = parser(inp)?;
+    inp.take_while(char::is_whitespace);
+    if inp.is_empty() {
+        Ok(v)
+    } else {
+        Err(inp.error(end, 1))
+    }
+}
+
+// Parse every non-blank line with the same parser
+pub fn lines<'a, T>(
+    src: impl Into<Input<'a>>,
+    mut parser: impl FnMut(&mut Input<'a>) -> Result<T>,
+) -> Result<Vec<T>> {
+    let mut inp = src.into();
+    let mut out = vec![];
+    for line in inp.rest().split_inclusive('\n') {
+        let end = inp.pos + line.len();
+        if !line.trim().is_empty() {
+            let mut l = inp.until(end);
+            l.skip_ws();
+            out.push(finish(&mut l, &mut parser, "end of line")?);
+        }
+        inp.pos = end;
+    }
+    Ok(out)
+}
+
+// Blank-line separated chunks of the input
+pub fn sections<'a>(src: impl Into<Input<'a>>) -> Vec<Input<'a>> {
+    let mut inp = src.into();
+    let mut out = vec![];
+    while !inp.is_empty() {
+        inp.take_while(char::is_whitespace);
+        let len = inp
+            .rest()
+            .split_inclusive('\n')
+            .take_while(|line| !line.trim().is_empty())
+            .map(str::len)
+            .sum::<usize>();
+        if len > 0 {
+            out.push(inp.until(inp.pos + len));
+        }
+        inp.pos += len;
+    }
+    out
+}
+
+// A rectangle of characters, one row per line. Ragged rows are an error.
+pub fn grid<'a, T>(
+    src: impl Into<Input<'a>>,
+    cell: impl Fn(char) -> Option<T>,
+) -> Result<Vec<Vec<T>>> {
+    let mut inp = src.into();
+    let mut rows: Vec<Vec<T>> = vec![];
+    for line in inp.rest().split_inclusive('\n') {
+        let row = line.trim_end();
+        if !row.is_empty() {
+            let mut cells = Vec::with_capacity(row.len());
+            for (i, ch) in row.char_indices() {
+                match cell(ch) {
+                    Some(c) => cells.push(c),
+                    None => return Err(Input::at(inp.src, inp.pos + i).error("a grid cell", 1)),
+                }
+            }
+            if let Some(first) = rows.first() {
+                if first.len() != cells.len() {
+                    return Err(inp.error(format!("a row of {} cells", first.len()), row.len()));
+                }
+            }
+            rows.push(cells);
+        }
+        inp.pos += line.len();
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers_and_ranges() {
+        assert_eq!(complete("-42", int::<i32>), Ok(-42));
+        assert_eq!(
+            complete("x=-20..26", |i| key_value(i, ident, range)),
+            Ok(("x", -20..=26))
+        );
+        assert!(complete("300", int::<u8>).is_err());
+    }
+
+    #[test]
+    fn separated() {
+        let steps = complete("on x=10..12,y=10..12,z=10..12", |i| {
+            let on = ident(i)? == "on";
+            i.skip_ws();
+            Ok((on, key_values(i, range::<i32>)?))
+        })
+        .unwrap();
+        assert!(steps.0);
+        assert_eq!(steps.1.len(), 3);
+        assert_eq!(steps.1[2], ("z", 10..=12));
+    }
+
+    #[test]
+    fn named_keys() {
+        assert_eq!(
+            complete("x=1..2", |i| named(i, "x", range::<i32>)),
+            Ok(1..=2)
+        );
+        let err = complete("y=1..2", |i| named(i, "x", range::<i32>)).unwrap_err();
+        assert_eq!((err.expected.as_str(), err.col), ("\"x\"", 1));
+    }
+
+    #[test]
+    fn error_spans() {
+        let err = lines("1,2\n3;4\n", |i| sep_by(i, int::<i32>, ",")).unwrap_err();
+        assert_eq!(err.span, 5..6);
+        assert_eq!((err.line, err.col), (2, 2));
+        assert_eq!(err.to_string(), "2:2: expected end of line, found \";\"");
+    }
+
+    #[test]
+    fn grids_and_sections() {
+        let g = grid("12\n34\n", |c| c.to_digit(10)).unwrap();
+        assert_eq!(g, vec![vec![1, 2], vec![3, 4]]);
+        let err = grid("12\n345\n", |c| c.to_digit(10)).unwrap_err();
+        assert_eq!(err.line, 2);
+        let s = sections("a\nb\n\n\nc\n");
+        assert_eq!(s.len(), 2);
+        assert_eq!(s[1].rest(), "c\n");
+        assert_eq!(s[1].pos(), 6);
+        let s = sections("a\r\nb\r\n\r\nc\r\n");
+        assert_eq!(s.len(), 2);
+        assert_eq!(s[0].rest(), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn multibyte_errors() {
+        let err = complete("é", int::<i32>).unwrap_err();
+        assert_eq!(err.found, "é");
+        assert!(complete("1é", int::<i32>).is_err());
+    }
+}
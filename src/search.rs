@@ -0,0 +1,289 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+
+// Anything that can list the (weighted) neighbours of a node. Closures of the
+// right shape get this for free, so callers can write the graph inline.
+pub trait Graph<N> {
+    fn neighbors(&self, node: &N) -> Vec<(N, u32)>;
+}
+
+impl<N, F> Graph<N> for F
+where
+    F: Fn(&N) -> Vec<(N, u32)>,
+{
+    fn neighbors(&self, node: &N) -> Vec<(N, u32)> {
+        self(node)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path<N> {
+    pub nodes: Vec<N>,
+    pub cost: u32,
+}
+
+// Hop count to every node reachable from start, ignoring edge weights.
+pub fn bfs<N, G>(graph: &G, start: N) -> HashMap<N, usize>
+where
+    N: Clone + Eq + Hash,
+    G: Graph<N>,
+{
+    let mut depth: HashMap<N, usize> = HashMap::new();
+    let mut todo: VecDeque<N> = VecDeque::new();
+
+    depth.insert(start.clone(), 0);
+    todo.push_back(start);
+
+    while let Some(node) = todo.pop_front() {
+        let d = depth[&node];
+        for (n, _) in graph.neighbors(&node) {
+            if !depth.contains_key(&n) {
+                depth.insert(n.clone(), d + 1);
+                todo.push_back(n);
+            }
+        }
+    }
+
+    depth
+}
+
+// Cheapest path from start to the first node satisfying is_goal.
+pub fn dijkstra<N, G>(graph: &G, start: N, is_goal: impl Fn(&N) -> bool) -> Option<Path<N>>
+where
+    N: Clone + Eq + Hash,
+    G: Graph<N>,
+{
+    astar(graph, start, is_goal, |_| 0)
+}
+
+// As dijkstra, but guided by a heuristic. The heuristic must never overestimate
+// the remaining cost (and should be consistent), or the path may not be optimal.
+pub fn astar<N, G>(
+    graph: &G,
+    start: N,
+    is_goal: impl Fn(&N) -> bool,
+    heuristic: impl Fn(&N) -> u32,
+) -> Option<Path<N>>
+where
+    N: Clone + Eq + Hash,
+    G: Graph<N>,
+{
+    // Nodes are interned so the heap only has to order plain indices
+    let mut nodes: Vec<N> = vec![start.clone()];
+    let mut index: HashMap<N, usize> = HashMap::new();
+    let mut dist: Vec<u32> = vec![0];
+    let mut prev: Vec<Option<usize>> = vec![None];
+
+    index.insert(start.clone(), 0);
+
+    // Reverse because we need a min-heap
+    let mut pq: BinaryHeap<(Reverse<u32>, Reverse<u32>, usize)> = BinaryHeap::new();
+    pq.push((Reverse(heuristic(&start)), Reverse(0), 0));
+
+    while let Some((_, Reverse(g), i)) = pq.pop() {
+        if g > dist[i] {
+            // Stale entry, we already found a shorter way here
+            continue;
+        }
+        if is_goal(&nodes[i]) {
+            let mut path = vec![nodes[i].clone()];
+            let mut cur = i;
+            while let Some(p) = prev[cur] {
+                path.push(nodes[p].clone());
+                cur = p;
+            }
+            path.reverse();
+            return Some(Path {
+                nodes: path,
+                cost: g,
+            });
+        }
+
+        for (n, w) in graph.neighbors(&nodes[i]) {
+            // Saturate rather than wrap, so huge weights still sort last
+            let via = g.saturating_add(w);
+            let j = match index.get(&n) {
+                Some(&j) => {
+                    if dist[j] <= via {
+                        continue;
+                    }
+                    dist[j] = via;
+                    prev[j] = Some(i);
+                    j
+                }
+                None => {
+                    nodes.push(n.clone());
+                    dist.push(via);
+                    prev.push(Some(i));
+                    index.insert(n.clone(), nodes.len() - 1);
+                    nodes.len() - 1
+                }
+            };
+            pq.push((Reverse(via.saturating_add(heuristic(&n))), Reverse(via), j));
+        }
+    }
+
+    None
+}
+
+// Every path from start that ends at a goal node, found depth-first. A node is
+// only stepped onto if admissible(path so far, node) allows it, and paths longer
+// than max_len nodes are abandoned. Reaching a goal ends that path.
+pub fn paths<N, G>(
+    graph: &G,
+    start: N,
+    is_goal: impl Fn(&N) -> bool,
+    admissible: impl Fn(&[N], &N) -> bool,
+    max_len: Option<usize>,
+) -> Vec<Vec<N>>
+where
+    N: Clone,
+    G: Graph<N>,
+{
+    let mut found: Vec<Vec<N>> = vec![];
+    let mut path: Vec<N> = vec![start];
+    extend_paths(
+        graph,
+        &is_goal,
+        &admissible,
+        max_len.unwrap_or(usize::MAX),
+        &mut path,
+        &mut |p: &[N]| found.push(p.to_vec()),
+    );
+    found
+}
+
+// As paths, but only counts them, without keeping any
+pub fn count_paths<N, G>(
+    graph: &G,
+    start: N,
+    is_goal: impl Fn(&N) -> bool,
+    admissible: impl Fn(&[N], &N) -> bool,
+    max_len: Option<usize>,
+) -> usize
+where
+    N: Clone,
+    G: Graph<N>,
+{
+    let mut count = 0;
+    let mut path: Vec<N> = vec![start];
+    extend_paths(
+        graph,
+        &is_goal,
+        &admissible,
+        max_len.unwrap_or(usize::MAX),
+        &mut path,
+        &mut |_: &[N]| count += 1,
+    );
+    count
+}
+
+fn extend_paths<N, G>(
+    graph: &G,
+    is_goal: &impl Fn(&N) -> bool,
+    admissible: &impl Fn(&[N], &N) -> bool,
+    max_len: usize,
+    path: &mut Vec<N>,
+    found: &mut impl FnMut(&[N]),
+) where
+    N: Clone,
+    G: Graph<N>,
+{
+    let node = path.last().unwrap();
+    if is_goal(node) {
+        found(path);
+        return;
+    }
+    if path.len() >= max_len {
+        return;
+    }
+
+    for (n, _) in graph.neighbors(node) {
+        if admissible(path, &n) {
+            path.push(n);
+            extend_paths(graph, is_goal, admissible, max_len, path, found);
+            path.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Point = (i32, i32);
+
+    // 5x5 open grid with a wall down x=2 except at y=4
+    fn grid(p: &Point) -> Vec<(Point, u32)> {
+        let (x, y) = *p;
+        [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .into_iter()
+            .filter(|&(nx, ny)| (0..5).contains(&nx) && (0..5).contains(&ny))
+            .filter(|&(nx, ny)| nx != 2 || ny == 4)
+            .map(|n| (n, 1))
+            .collect()
+    }
+
+    #[test]
+    fn bfs_depths() {
+        let depth = bfs(&grid, (0, 0));
+        assert_eq!(depth.len(), 21);
+        assert_eq!(depth[&(4, 0)], 12);
+    }
+
+    #[test]
+    fn dijkstra_matches_astar() {
+        let d = dijkstra(&grid, (0, 0), |&p| p == (4, 0)).unwrap();
+        let a = astar(
+            &grid,
+            (0, 0),
+            |&p| p == (4, 0),
+            |&(x, y)| ((4 - x).abs() + y.abs()) as u32,
+        )
+        .unwrap();
+        assert_eq!(d.cost, 12);
+        assert_eq!(a.cost, 12);
+        assert_eq!(a.nodes.len(), 13);
+        assert_eq!(a.nodes.first(), Some(&(0, 0)));
+        assert_eq!(a.nodes.last(), Some(&(4, 0)));
+    }
+
+    #[test]
+    fn weighted_prefers_cheap_detour() {
+        let g = |n: &char| match n {
+            'a' => vec![('b', 10), ('c', 1)],
+            'c' => vec![('d', 1)],
+            'd' => vec![('b', 1)],
+            _ => vec![],
+        };
+        let p = dijkstra(&g, 'a', |&n| n == 'b').unwrap();
+        assert_eq!(p.cost, 3);
+        assert_eq!(p.nodes, vec!['a', 'c', 'd', 'b']);
+        assert_eq!(dijkstra(&g, 'b', |&n| n == 'a'), None);
+
+        // Costs that would overflow saturate instead
+        let huge = |n: &char| match n {
+            'a' => vec![('b', u32::MAX), ('c', u32::MAX)],
+            'b' => vec![('d', 5)],
+            _ => vec![],
+        };
+        assert_eq!(dijkstra(&huge, 'a', |&n| n == 'd').unwrap().cost, u32::MAX);
+    }
+
+    #[test]
+    fn simple_paths() {
+        let g = |n: &u8| match n {
+            0 => vec![(1, 1), (2, 1)],
+            1 => vec![(2, 1), (3, 1)],
+            2 => vec![(3, 1)],
+            _ => vec![],
+        };
+        let all = paths(&g, 0, |&n| n == 3, |_, _| true, None);
+        assert_eq!(all.len(), 3);
+        let short = paths(&g, 0, |&n| n == 3, |_, _| true, Some(3));
+        assert_eq!(short.len(), 2);
+        assert_eq!(count_paths(&g, 0, |&n| n == 3, |_, _| true, None), 3);
+        assert_eq!(count_paths(&g, 0, |&n| n == 3, |_, _| true, Some(3)), 2);
+    }
+}